@@ -0,0 +1,50 @@
+// Procedural nebula clouds, drawn as fractal value noise over world coordinates
+
+#import bevy_sprite::mesh2d_vertex_output::VertexOutput
+
+@group(2) @binding(0)
+var<uniform> tint: vec4<f32>;
+
+// xy: layer offset, z: noise scale, w: seed
+@group(2) @binding(1)
+var<uniform> params: vec4<f32>;
+
+fn hash(p: vec2<f32>) -> f32 {
+    return fract(sin(dot(p, vec2<f32>(127.1, 311.7))) * 43758.5453);
+}
+
+fn noise(p: vec2<f32>) -> f32 {
+    let i = floor(p);
+    let f = fract(p);
+    let u = f * f * (3.0 - 2.0 * f);
+
+    let a = hash(i);
+    let b = hash(i + vec2<f32>(1.0, 0.0));
+    let c = hash(i + vec2<f32>(0.0, 1.0));
+    let d = hash(i + vec2<f32>(1.0, 1.0));
+
+    return mix(mix(a, b, u.x), mix(c, d, u.x), u.y);
+}
+
+fn fbm(p: vec2<f32>) -> f32 {
+    var value = 0.0;
+    var amplitude = 0.5;
+    var q = p;
+    for (var i = 0; i < 5; i = i + 1) {
+        value = value + amplitude * noise(q);
+        q = q * 2.0 + vec2<f32>(17.3, 9.1);
+        amplitude = amplitude * 0.5;
+    }
+    return value;
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    let p = (in.world_position.xy - params.xy) / params.z + vec2<f32>(params.w, params.w * 0.37);
+
+    // Warp the domain once for wispier shapes
+    let warp = vec2<f32>(fbm(p + vec2<f32>(3.1, 1.7)), fbm(p + vec2<f32>(8.3, 2.8)));
+    let density = smoothstep(0.45, 0.85, fbm(p + warp * 1.5));
+
+    return vec4<f32>(tint.rgb, tint.a * density);
+}
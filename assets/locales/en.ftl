@@ -0,0 +1,121 @@
+# Trailing spaces are written as { " " } to keep them
+
+window-title = Bevy Workshop
+
+splash-title = Bevy Workshop
+splash-subtitle = Rust Week 2025
+
+## Start menu
+
+menu-title = Asteroid
+menu-start = Press any key to start
+menu-endless = Press E for endless survival
+menu-multiplayer = Press C for two player co-op, V for versus
+menu-settings = Press S for settings
+high-score-campaign = { $score } - campaign
+high-score-endless = { $score } - endless, wave { $wave }
+high-score-coop = { $score } - co-op
+high-score-versus = { $score } - versus
+high-score-assisted = { " " }(assisted)
+
+## Pause menu
+
+pause-title = Paused
+pause-resume = Press Esc to resume
+pause-settings = Press S for settings
+pause-quit = Press Q to quit to the menu
+
+## End of the game
+
+won-solo = You Won!
+won-player = Player { $player } Won!
+won-back = Press any key to go back to the menu
+
+## HUD
+
+hud-asteroids = Asteroids remaining:{ " " }
+hud-lives = Lives remaining:{ " " }
+hud-time = Time:{ " " }
+hud-shield = Shield:{ " " }
+hud-score = Score:{ " " }
+hud-wave = Wave:{ " " }
+hud-objectives = Objectives:{ " " }
+hud-seconds = { $seconds }s
+respawn-player = Player { $player }:{ " " }
+respawn-waiting = Waiting for a clear area...
+respawn-press = Press { $key } to respawn
+
+## Objectives
+
+objective-clear = Clear asteroids: { $count } left
+objective-time = Time left: { $seconds }s
+objective-score = Reach score: { $score }/{ $target }
+objective-survive = Survive: { $elapsed }/{ $duration }s
+objective-ammo = Ammo: { $ammo }
+
+## Network play
+
+net-waiting = Waiting for the server...
+net-pilot = Player { $player }: { $lives } lives, { $score } points
+net-desync = Out of sync with the other player at frame { $frame }, the match was stopped
+
+## Settings
+
+settings-title = Settings
+settings-hint = Up/Down to select, Left/Right to adjust, Enter to change, Esc to go back
+settings-press-key = Press a key... (Esc to cancel)
+settings-display = Display
+settings-graphics = Graphics
+settings-audio = Audio
+settings-accessibility = Accessibility
+settings-controls = Controls
+settings-assists = Assists (marked on high scores)
+on = on
+off = off
+
+settings-language = Language: { $value }
+language-en = English
+language-ru = Russian
+settings-fullscreen = Fullscreen: { $value }
+settings-vsync = VSync: { $value }
+
+settings-hdr = HDR: { $value }
+settings-bloom = Bloom: { $value }
+settings-bloom-needs-hdr = Bloom: { $value } (needs HDR)
+bloom-off = off
+bloom-low = low
+bloom-high = high
+settings-bloom-intensity = Bloom intensity: { $value }
+settings-bloom-threshold = Bloom threshold: { $value }
+settings-particles = Particles: { $value }%
+settings-starfield = Starfield quality: { $value }
+starfield-low = low
+starfield-high = high
+
+settings-volume = { $bus }: { $value }%
+settings-volume-muted = { $bus }: { $value }% (muted)
+bus-master = Master volume
+bus-music = Music volume
+bus-sfx = Sound effects volume
+bus-ui = Interface volume
+
+settings-key = Player { $player } { $action }: { $key }
+action-rotate_left = rotate left
+action-rotate_right = rotate right
+action-thrust = thrust
+action-fire = fire
+action-hyperspace = hyperspace
+
+settings-reduced-motion = Reduced motion: { $value }
+settings-palette = Colours: { $value }
+palette-default = default
+palette-deuteranopia = deuteranopia
+palette-protanopia = protanopia
+palette-tritanopia = tritanopia
+palette-high_contrast = high contrast
+
+settings-game-speed = Game speed: { $value }%
+settings-toggle-thrust = Toggle thrust: { $value }
+settings-auto-fire = Auto-fire: { $value }
+settings-aim-assist = Aim assist: { $value }
+settings-infinite-lives = Infinite lives: { $value }
@@ -0,0 +1,121 @@
+# Trailing spaces are written as { " " } to keep them
+
+window-title = Bevy Workshop
+
+splash-title = Bevy Workshop
+splash-subtitle = Неделя Rust 2025
+
+## Start menu
+
+menu-title = Астероид
+menu-start = Нажмите любую клавишу, чтобы начать
+menu-endless = Нажмите E для бесконечного выживания
+menu-multiplayer = Нажмите C для игры вдвоём, V для дуэли
+menu-settings = Нажмите S для настроек
+high-score-campaign = { $score } — кампания
+high-score-endless = { $score } — выживание, волна { $wave }
+high-score-coop = { $score } — вдвоём
+high-score-versus = { $score } — дуэль
+high-score-assisted = { " " }(с помощью)
+
+## Pause menu
+
+pause-title = Пауза
+pause-resume = Нажмите Esc, чтобы продолжить
+pause-settings = Нажмите S для настроек
+pause-quit = Нажмите Q, чтобы выйти в меню
+
+## End of the game
+
+won-solo = Победа!
+won-player = Игрок { $player } победил!
+won-back = Нажмите любую клавишу, чтобы вернуться в меню
+
+## HUD
+
+hud-asteroids = Осталось астероидов:{ " " }
+hud-lives = Осталось жизней:{ " " }
+hud-time = Время:{ " " }
+hud-shield = Щит:{ " " }
+hud-score = Очки:{ " " }
+hud-wave = Волна:{ " " }
+hud-objectives = Задачи:{ " " }
+hud-seconds = { $seconds } с
+respawn-player = Игрок { $player }:{ " " }
+respawn-waiting = Ожидание свободного места...
+respawn-press = Нажмите { $key }, чтобы возродиться
+
+## Objectives
+
+objective-clear = Уничтожить астероиды: осталось { $count }
+objective-time = Осталось времени: { $seconds } с
+objective-score = Набрать очки: { $score }/{ $target }
+objective-survive = Продержаться: { $elapsed }/{ $duration } с
+objective-ammo = Боеприпасы: { $ammo }
+
+## Network play
+
+net-waiting = Ожидание сервера...
+net-pilot = Игрок { $player }: жизни { $lives }, очки { $score }
+net-desync = Рассинхронизация с другим игроком на кадре { $frame }, матч остановлен
+
+## Settings
+
+settings-title = Настройки
+settings-hint = Вверх/вниз — выбор, влево/вправо — настройка, Enter — изменить, Esc — назад
+settings-press-key = Нажмите клавишу... (Esc — отмена)
+settings-display = Экран
+settings-graphics = Графика
+settings-audio = Звук
+settings-accessibility = Доступность
+settings-controls = Управление
+settings-assists = Помощь (отмечается в рекордах)
+on = вкл
+off = выкл
+
+settings-language = Язык: { $value }
+language-en = английский
+language-ru = русский
+settings-fullscreen = Полный экран: { $value }
+settings-vsync = Вертикальная синхронизация: { $value }
+
+settings-hdr = HDR: { $value }
+settings-bloom = Свечение: { $value }
+settings-bloom-needs-hdr = Свечение: { $value } (нужен HDR)
+bloom-off = выкл
+bloom-low = низкое
+bloom-high = высокое
+settings-bloom-intensity = Сила свечения: { $value }
+settings-bloom-threshold = Порог свечения: { $value }
+settings-particles = Частицы: { $value }%
+settings-starfield = Качество звёзд: { $value }
+starfield-low = низкое
+starfield-high = высокое
+
+settings-volume = { $bus }: { $value }%
+settings-volume-muted = { $bus }: { $value }% (без звука)
+bus-master = Общая громкость
+bus-music = Громкость музыки
+bus-sfx = Громкость эффектов
+bus-ui = Громкость интерфейса
+
+settings-key = Игрок { $player }, { $action }: { $key }
+action-rotate_left = поворот влево
+action-rotate_right = поворот вправо
+action-thrust = тяга
+action-fire = огонь
+action-hyperspace = гиперпрыжок
+
+settings-reduced-motion = Меньше движения: { $value }
+settings-palette = Цвета: { $value }
+palette-default = стандартные
+palette-deuteranopia = дейтеранопия
+palette-protanopia = протанопия
+palette-tritanopia = тританопия
+palette-high_contrast = высокий контраст
+
+settings-game-speed = Скорость игры: { $value }%
+settings-toggle-thrust = Тяга переключением: { $value }
+settings-auto-fire = Автоогонь: { $value }
+settings-aim-assist = Помощь в прицеливании: { $value }
+settings-infinite-lives = Бесконечные жизни: { $value }
@@ -15,7 +15,9 @@ fn rand(p: vec2<f32>) -> f32 {
     return fract(sin(dot(p, vec2<f32>(54.90898, 18.233))) * 4337.5453);
 }
 
-fn stars(x: vec2<f32>, num_cells: f32, size: f32, br: f32) -> f32 {
+// layer: x = density, y = size, z = brightness, w = parallax
+fn stars(x: vec2<f32>, layer: vec4<f32>) -> f32 {
+    let num_cells = layer.x;
     let n = x * num_cells;
     let f = floor(n);
 
@@ -24,17 +26,19 @@ fn stars(x: vec2<f32>, num_cells: f32, size: f32, br: f32) -> f32 {
         for (var j = -1; j <= 1; j = j + 1) {
             var g = f + vec2<f32>(f32(i), f32(j));
 			g = n - g - rand2(g % num_cells) + rand(g);
-            g = g / (num_cells * size);
+            g = g / (num_cells * layer.y);
 			d = min(d, dot(g, g));
         }
     }
 
-    return br * (smoothstep(.95, 1., (1. - sqrt(d))));
+    return layer.z * (smoothstep(.95, 1., (1. - sqrt(d))));
 }
 
 struct Material {
     coords: vec2<f32>,
     seeds: vec2<f32>,
+    layers: array<vec4<f32>, 3>,
+    twinkle: f32,
 }
 
 @group(2) @binding(0)
@@ -42,16 +46,18 @@ var<uniform> material: Material;
 
 @fragment
 fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
-    var result = vec3<f32>(0.0, 0.0, 0.0);
+    var result = 0.0;
     let coords = vec2<f32>(-material.coords.x, material.coords.y);
     let move_factor = 1000.0;
-    let time_factor = 0.05;
 
-    let intensity = clamp(rand(in.uv * globals.time), 0.4, 1.0);
+    let intensity = clamp(rand(in.uv * globals.time), 1.0 - material.twinkle, 1.0);
 
-    result = result + stars(in.uv + vec2<f32>(material.seeds.x, 0.0) - coords / (move_factor * 1.2), 3.0, 0.025, 2.0) * vec3<f32>(3.6, 3.6, 3.6) * intensity;
-    result = result + stars(in.uv + vec2<f32>(material.seeds.y, 0.0) - coords / (move_factor * 1.4), 10.0, 0.018, 1.0) * vec3<f32>(6.7, 6.7, 6.7) * intensity;
-    result = result + stars(in.uv + vec2<f32>(material.seeds.x, material.seeds.y) - coords / (move_factor * 2.0), 30.0, 0.015, 0.5) * vec3<f32>(.75, .75, .75) * intensity;
+    let layer0 = material.layers[0];
+    let layer1 = material.layers[1];
+    let layer2 = material.layers[2];
+    result = result + stars(in.uv + vec2<f32>(material.seeds.x, 0.0) - coords * layer0.w / move_factor, layer0);
+    result = result + stars(in.uv + vec2<f32>(material.seeds.y, 0.0) - coords * layer1.w / move_factor, layer1);
+    result = result + stars(in.uv + material.seeds - coords * layer2.w / move_factor, layer2);
 
-    return vec4<f32>(result * 6.0, 1.0);
+    return vec4<f32>(vec3<f32>(result * intensity), 1.0);
 }
@@ -0,0 +1,168 @@
+use std::f32::consts::PI;
+
+use avian2d::prelude::*;
+use bevy::prelude::*;
+use bevy_enhanced_input::{action_map::ActionMap, input_condition::DEFAULT_ACTUATION, prelude::*};
+
+use crate::{
+    GameState,
+    game::{FireLaser, NearestAsteroid, Player},
+    pause::Pause,
+    settings::Settings,
+};
+
+// Lasers travel this far before they expire
+const AUTO_FIRE_RANGE: f32 = 900.0;
+const AUTO_FIRE_ANGLE: f32 = PI / 18.0;
+const AIM_ASSIST_ANGLE: f32 = PI / 6.0;
+// How fast aim assist turns the ship toward its target, per second
+const AIM_ASSIST_RATE: f32 = 6.0;
+
+pub fn assists_plugin(app: &mut App) {
+    app.add_systems(OnEnter(GameState::Game), apply_game_speed)
+        .add_systems(
+            Update,
+            (
+                apply_game_speed.run_if(resource_changed::<Settings>),
+                (auto_fire, aim_assist).run_if(in_state(Pause::Running)),
+            )
+                .run_if(in_state(GameState::Game)),
+        );
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Assists {
+    // Relative speed of the game, from 0.5 to 1.0
+    pub game_speed: f32,
+    pub toggle_thrust: bool,
+    pub auto_fire: bool,
+    pub aim_assist: bool,
+    pub infinite_lives: bool,
+}
+
+impl Default for Assists {
+    fn default() -> Self {
+        Assists {
+            game_speed: 1.0,
+            toggle_thrust: false,
+            auto_fire: false,
+            aim_assist: false,
+            infinite_lives: false,
+        }
+    }
+}
+
+impl Assists {
+    pub fn any(&self) -> bool {
+        self.game_speed < 1.0
+            || self.toggle_thrust
+            || self.auto_fire
+            || self.aim_assist
+            || self.infinite_lives
+    }
+}
+
+// Fires on one press and keeps firing until the next one, instead of while held
+#[derive(Debug, Default)]
+pub struct Toggle {
+    on: bool,
+    pressed: bool,
+}
+
+impl Toggle {
+    // Whether the toggled input is on after this frame
+    pub fn press(&mut self, pressed: bool) -> bool {
+        if pressed && !self.pressed {
+            self.on = !self.on;
+        }
+        self.pressed = pressed;
+        self.on
+    }
+}
+
+impl InputCondition for Toggle {
+    fn evaluate(
+        &mut self,
+        _action_map: &ActionMap,
+        time: &Time<Virtual>,
+        value: ActionValue,
+    ) -> ActionState {
+        // Don't keep going while the game is paused
+        if self.press(value.is_actuated(DEFAULT_ACTUATION)) && !time.is_paused() {
+            ActionState::Fired
+        } else {
+            ActionState::None
+        }
+    }
+}
+
+fn apply_game_speed(mut time: ResMut<Time<Virtual>>, settings: Res<Settings>) {
+    time.set_relative_speed(settings.assists.game_speed);
+}
+
+// Angle a ship needs to turn to face its target, and the target's distance
+pub fn aim(position: Vec2, heading: Vec2, target: Vec2) -> (f32, f32) {
+    let target = target - position;
+    (heading.angle_to(target), target.length())
+}
+
+pub fn auto_fire_at(angle: f32, distance: f32) -> bool {
+    angle.abs() < AUTO_FIRE_ANGLE && distance < AUTO_FIRE_RANGE
+}
+
+pub fn aim_assist_at(angle: f32) -> bool {
+    angle.abs() < AIM_ASSIST_ANGLE
+}
+
+fn aim_nearest(transform: &Transform, nearest: &NearestAsteroid) -> Option<(f32, f32)> {
+    Some(aim(
+        transform.translation.xy(),
+        transform.local_y().xy(),
+        nearest.0?,
+    ))
+}
+
+fn auto_fire(
+    mut commands: Commands,
+    ships: Query<(Entity, &Transform, &NearestAsteroid), With<Player>>,
+    settings: Res<Settings>,
+) {
+    if !settings.assists.auto_fire {
+        return;
+    }
+    for (entity, transform, nearest) in &ships {
+        if let Some((angle, distance)) = aim_nearest(transform, nearest)
+            && auto_fire_at(angle, distance)
+        {
+            // Fire rate is limited by the laser's own cooldown
+            commands.trigger_targets(
+                Fired::<FireLaser> {
+                    value: true,
+                    state: ActionState::Fired,
+                    fired_secs: 0.0,
+                    elapsed_secs: 0.0,
+                },
+                entity,
+            );
+        }
+    }
+}
+
+fn aim_assist(
+    mut ships: Query<(&Transform, &NearestAsteroid, &mut AngularVelocity), With<Player>>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+) {
+    if !settings.assists.aim_assist {
+        return;
+    }
+    for (transform, nearest, mut angular_velocity) in &mut ships {
+        if let Some((angle, _)) = aim_nearest(transform, nearest)
+            && aim_assist_at(angle)
+        {
+            let target = angle * AIM_ASSIST_RATE;
+            angular_velocity.0 +=
+                (target - angular_velocity.0) * (AIM_ASSIST_RATE * time.delta_secs()).min(1.0);
+        }
+    }
+}
@@ -1,44 +1,298 @@
-use bevy::prelude::*;
+use std::{borrow::Cow, collections::HashMap, time::Duration};
 
-use crate::AudioAssets;
+use bevy::{
+    asset::{AssetLoader, AsyncReadExt, LoadContext, io::Reader},
+    audio::{SpatialScale, Volume},
+    prelude::*,
+};
+use rand::{Rng, seq::SliceRandom};
+use serde::Deserialize;
+use thiserror::Error;
 
-#[derive(Event)]
-pub enum AudioStart {
-    Laser,
-    ShipExplosion,
-    AsteroidExplosion,
-    Start,
-    Win,
-    Lose,
+use crate::{AudioAssets, music::MusicStem, settings::Settings};
+
+// Plays a cue from the sound bank. In-world sounds carry the position of their
+// emitter, UI sounds have none.
+#[derive(Event, Clone, Debug)]
+pub struct AudioStart {
+    pub cue: Cow<'static, str>,
+    pub position: Option<Vec2>,
+}
+
+impl AudioStart {
+    pub fn new(cue: impl Into<Cow<'static, str>>) -> Self {
+        AudioStart {
+            cue: cue.into(),
+            position: None,
+        }
+    }
+
+    pub fn at(cue: impl Into<Cow<'static, str>>, position: Vec2) -> Self {
+        AudioStart {
+            cue: cue.into(),
+            position: Some(position),
+        }
+    }
 }
 
+// Distance between the listener's ears, in world units. Sounds closer than half
+// of it play at full volume, and fall off with distance beyond that.
+const EAR_GAP: f32 = 400.0;
+const SPATIAL_SCALE: SpatialScale = SpatialScale(Vec3::splat(1.0 / EAR_GAP));
+
 pub fn audio_plugin(app: &mut App) {
     app.add_event::<AudioStart>()
-        .add_systems(Update, play_audio);
+        .add_plugins(sound_bank_plugin)
+        .add_observer(route_to_bus)
+        .add_observer(add_listener)
+        .add_systems(
+            Update,
+            (play_audio, apply_mixer.run_if(resource_changed::<Settings>)),
+        );
 }
 
-impl AudioStart {
-    fn to_handle(&self, audio_assets: &AudioAssets) -> Handle<AudioSource> {
-        match self {
-            AudioStart::Laser => audio_assets.laser.clone(),
-            AudioStart::ShipExplosion => audio_assets.ship_explosion.clone(),
-            AudioStart::AsteroidExplosion => audio_assets.asteroid_explosion.clone(),
-            AudioStart::Start => audio_assets.start.clone(),
-            AudioStart::Win => audio_assets.win.clone(),
-            AudioStart::Lose => audio_assets.lose.clone(),
+// The headless server has no audio but still loads the sound bank with the other assets
+pub fn sound_bank_plugin(app: &mut App) {
+    app.init_asset::<SoundBank>()
+        .init_asset_loader::<SoundBankLoader>();
+}
+
+fn add_listener(trigger: Trigger<OnAdd, Camera2d>, mut commands: Commands) {
+    commands
+        .entity(trigger.target())
+        .insert(SpatialListener::new(EAR_GAP));
+}
+
+// Every audio player goes through a bus, and all buses go through the master
+#[derive(Component, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AudioBus {
+    Music,
+    Sfx,
+    Ui,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct BusSettings {
+    pub volume: f32,
+    pub muted: bool,
+}
+
+impl Default for BusSettings {
+    fn default() -> Self {
+        BusSettings {
+            volume: 1.0,
+            muted: false,
+        }
+    }
+}
+
+impl BusSettings {
+    fn gain(&self) -> f32 {
+        if self.muted { 0.0 } else { self.volume }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Mixer {
+    pub master: BusSettings,
+    pub music: BusSettings,
+    pub sfx: BusSettings,
+    pub ui: BusSettings,
+}
+
+impl Mixer {
+    pub fn gain(&self, bus: AudioBus) -> Volume {
+        let bus = match bus {
+            AudioBus::Music => &self.music,
+            AudioBus::Sfx => &self.sfx,
+            AudioBus::Ui => &self.ui,
+        };
+        Volume::Linear(self.master.gain() * bus.gain())
+    }
+
+    pub fn buses(&self) -> [(&'static str, &BusSettings); 4] {
+        [
+            ("master", &self.master),
+            ("music", &self.music),
+            ("sfx", &self.sfx),
+            ("ui", &self.ui),
+        ]
+    }
+
+    pub fn buses_mut(&mut self) -> [(&'static str, &mut BusSettings); 4] {
+        [
+            ("master", &mut self.master),
+            ("music", &mut self.music),
+            ("sfx", &mut self.sfx),
+            ("ui", &mut self.ui),
+        ]
+    }
+}
+
+// Volume the player was spawned with, before the mixer is applied
+#[derive(Component)]
+struct BaseVolume(Volume);
+
+fn route_to_bus(
+    trigger: Trigger<OnAdd, AudioBus>,
+    mut commands: Commands,
+    mut players: Query<(&AudioBus, &mut PlaybackSettings)>,
+    settings: Res<Settings>,
+) {
+    let Ok((bus, mut playback)) = players.get_mut(trigger.target()) else {
+        return;
+    };
+    commands
+        .entity(trigger.target())
+        .insert(BaseVolume(playback.volume));
+    playback.volume *= settings.mixer.gain(*bus);
+}
+
+// Music stems are left to mix_stems, which sets their volume every frame
+fn apply_mixer(
+    mut sinks: Query<(&AudioBus, &BaseVolume, &mut AudioSink), Without<MusicStem>>,
+    mut spatial_sinks: Query<(&AudioBus, &BaseVolume, &mut SpatialAudioSink)>,
+    settings: Res<Settings>,
+) {
+    for (bus, base, mut sink) in &mut sinks {
+        sink.set_volume(base.0 * settings.mixer.gain(*bus));
+    }
+    for (bus, base, mut sink) in &mut spatial_sinks {
+        sink.set_volume(base.0 * settings.mixer.gain(*bus));
+    }
+}
+
+#[derive(Asset, TypePath, Deserialize)]
+pub struct SoundBank {
+    cues: HashMap<String, Cue>,
+}
+
+#[derive(Deserialize)]
+struct Cue {
+    files: Vec<String>,
+    // Loaded from `files` by the loader
+    #[serde(skip)]
+    variants: Vec<Handle<AudioSource>>,
+    bus: AudioBus,
+    #[serde(default = "default_volume")]
+    volume: f32,
+    #[serde(default = "default_max_voices")]
+    max_voices: usize,
+    // Maximum relative deviation from the nominal pitch and volume
+    #[serde(default)]
+    pitch_variation: f32,
+    #[serde(default)]
+    volume_variation: f32,
+}
+
+fn default_volume() -> f32 {
+    1.0
+}
+
+fn default_max_voices() -> usize {
+    1
+}
+
+#[derive(Default)]
+struct SoundBankLoader;
+
+#[derive(Debug, Error)]
+pub enum SoundBankLoaderError {
+    #[error("Could not load asset: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Error in file format: {0}")]
+    FormatError(#[from] ron::error::SpannedError),
+}
+
+impl AssetLoader for SoundBankLoader {
+    type Asset = SoundBank;
+    type Settings = ();
+    type Error = SoundBankLoaderError;
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut buf = String::new();
+        reader.read_to_string(&mut buf).await?;
+        let mut sound_bank: SoundBank = ron::from_str(&buf)?;
+
+        for cue in sound_bank.cues.values_mut() {
+            cue.variants = cue
+                .files
+                .iter()
+                .map(|path| load_context.load(path))
+                .collect();
         }
+        Ok(sound_bank)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["bank.ron"]
     }
 }
 
+#[derive(Component)]
+struct Voice {
+    cue: Cow<'static, str>,
+    started: Duration,
+}
+
 fn play_audio(
     mut commands: Commands,
     mut audio_triggers: EventReader<AudioStart>,
     sound_assets: Res<AudioAssets>,
+    sound_banks: Res<Assets<SoundBank>>,
+    voices: Query<(Entity, &Voice)>,
+    time: Res<Time<Real>>,
 ) {
+    let Some(sound_bank) = sound_banks.get(&sound_assets.sound_bank) else {
+        return;
+    };
+    let mut playing: Vec<_> = voices
+        .iter()
+        .map(|(entity, voice)| (entity, voice.cue.clone(), voice.started))
+        .collect();
+    let mut rng = rand::thread_rng();
+
     for trigger in audio_triggers.read() {
-        commands.spawn((
-            AudioPlayer::<AudioSource>(trigger.to_handle(sound_assets.as_ref())),
-            PlaybackSettings::DESPAWN,
-        ));
+        let Some(cue) = sound_bank.cues.get(trigger.cue.as_ref()) else {
+            warn!("Unknown sound cue {}", trigger.cue);
+            continue;
+        };
+        // When the cue is at its limit, steal the oldest voice
+        let same_cue = playing.iter().filter(|(_, id, _)| *id == trigger.cue);
+        if same_cue.clone().count() >= cue.max_voices
+            && let Some((oldest, _, _)) = same_cue.min_by_key(|(_, _, started)| *started)
+        {
+            let oldest = *oldest;
+            commands.entity(oldest).despawn();
+            playing.retain(|(entity, _, _)| *entity != oldest);
+        }
+
+        let Some(handle) = cue.variants.choose(&mut rng) else {
+            continue;
+        };
+        let pitch = 1.0 + rng.gen_range(-1.0..=1.0) * cue.pitch_variation;
+        let volume = 1.0 - rng.gen_range(0.0..=1.0) * cue.volume_variation;
+        let playback = PlaybackSettings::DESPAWN
+            .with_spatial(trigger.position.is_some())
+            .with_spatial_scale(SPATIAL_SCALE)
+            .with_speed(pitch)
+            .with_volume(Volume::Linear(cue.volume * volume));
+        let entity = commands
+            .spawn((
+                AudioPlayer::<AudioSource>(handle.clone()),
+                playback,
+                cue.bus,
+                Voice {
+                    cue: trigger.cue.clone(),
+                    started: time.elapsed(),
+                },
+                Transform::from_translation(trigger.position.unwrap_or_default().extend(0.0)),
+            ))
+            .id();
+        playing.push((entity, trigger.cue.clone(), time.elapsed()));
     }
 }
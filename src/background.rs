@@ -0,0 +1,205 @@
+use bevy::{
+    prelude::*,
+    render::render_resource::{AsBindGroup, ShaderRef},
+    sprite::{AlphaMode2d, Material2d, Material2dPlugin},
+};
+
+use crate::{
+    GameState,
+    level::{BackgroundKind, CurrentLevel},
+};
+
+pub fn background_plugin(app: &mut App) {
+    app.add_plugins(Material2dPlugin::<CloudsMaterial>::default())
+        .add_systems(OnEnter(GameState::Game), setup)
+        .add_systems(
+            PostUpdate,
+            (update_tiles, update_planets, update_clouds).run_if(in_state(GameState::Game)),
+        );
+}
+
+// Layers are drawn between the starfield and the gameplay
+const BACKGROUND_DEPTH: f32 = -1.9;
+const LAYER_SPACING: f32 = 0.05;
+
+#[derive(Component)]
+struct BackgroundLayer {
+    parallax: f32,
+}
+
+#[derive(Component)]
+struct Tiles {
+    scale: f32,
+}
+
+#[derive(Component)]
+struct Planet {
+    position: Vec2,
+}
+
+#[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
+pub struct CloudsMaterial {
+    #[uniform(0)]
+    tint: LinearRgba,
+    // xy: layer offset, z: noise scale, w: seed
+    #[uniform(1)]
+    params: Vec4,
+}
+
+impl Material2d for CloudsMaterial {
+    fn fragment_shader() -> ShaderRef {
+        ShaderRef::Path("clouds.wgsl".into())
+    }
+
+    fn alpha_mode(&self) -> AlphaMode2d {
+        AlphaMode2d::Blend
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<CloudsMaterial>>,
+    asset_server: Res<AssetServer>,
+    level: CurrentLevel,
+) {
+    for (i, layer) in level.get().backgrounds.iter().enumerate() {
+        let transform = Transform::from_xyz(0.0, 0.0, BACKGROUND_DEPTH + LAYER_SPACING * i as f32);
+        let mut entity = commands.spawn((
+            BackgroundLayer {
+                parallax: layer.parallax,
+            },
+            transform,
+            StateScoped(GameState::Game),
+        ));
+        match &layer.kind {
+            BackgroundKind::Tiled { image, scale } => {
+                entity.insert((
+                    Sprite {
+                        image: asset_server.load(image),
+                        color: layer.tint,
+                        image_mode: SpriteImageMode::Tiled {
+                            tile_x: true,
+                            tile_y: true,
+                            stretch_value: *scale,
+                        },
+                        ..default()
+                    },
+                    Tiles { scale: *scale },
+                    Visibility::Hidden,
+                ));
+            }
+            BackgroundKind::Planet {
+                image,
+                position,
+                scale,
+            } => {
+                entity.insert((
+                    Sprite {
+                        image: asset_server.load(image),
+                        color: layer.tint,
+                        ..default()
+                    },
+                    transform.with_scale(Vec3::new(*scale, *scale, 1.0)),
+                    Planet {
+                        position: *position,
+                    },
+                ));
+            }
+            BackgroundKind::Clouds { seed, scale } => {
+                entity.insert((
+                    Mesh2d(meshes.add(Rectangle::default())),
+                    MeshMaterial2d(materials.add(CloudsMaterial {
+                        tint: layer.tint.into(),
+                        params: Vec4::new(0.0, 0.0, *scale, *seed),
+                    })),
+                ));
+            }
+        }
+    }
+}
+
+fn view_size(projection: &Projection, window: &Window) -> Vec2 {
+    let scale = match projection {
+        Projection::Orthographic(orthographic) => orthographic.scale,
+        _ => 1.0,
+    };
+    window.size() * scale
+}
+
+fn update_tiles(
+    mut tiles: Query<
+        (
+            &mut Transform,
+            &mut Sprite,
+            &mut Visibility,
+            &Tiles,
+            &BackgroundLayer,
+        ),
+        Without<Camera2d>,
+    >,
+    camera: Query<(&Transform, &Projection), With<Camera2d>>,
+    windows: Query<&Window>,
+    images: Res<Assets<Image>>,
+) {
+    let (camera_transform, projection) = camera.single().unwrap();
+    let view = view_size(projection, windows.single().unwrap());
+    let camera_position = camera_transform.translation.xy();
+
+    for (mut transform, mut sprite, mut visibility, tiles, layer) in &mut tiles {
+        let Some(image) = images.get(&sprite.image) else {
+            continue;
+        };
+        let tile = image.size_f32() * tiles.scale;
+
+        // Cover the view with whole tiles plus a margin, and shift by less than
+        // a tile so that the pattern scrolls at the layer's speed
+        let size = ((view / tile).ceil() + 2.0) * tile;
+        let offset = (camera_position * layer.parallax).rem_euclid(tile);
+        transform.translation = (camera_position - offset).extend(transform.translation.z);
+        if sprite.custom_size != Some(size) {
+            sprite.custom_size = Some(size);
+        }
+        visibility.set_if_neq(Visibility::Inherited);
+    }
+}
+
+fn update_planets(
+    mut planets: Query<(&mut Transform, &Planet, &BackgroundLayer), Without<Camera2d>>,
+    camera: Query<&Transform, With<Camera2d>>,
+) {
+    let camera_position = camera.single().unwrap().translation.xy();
+
+    for (mut transform, planet, layer) in &mut planets {
+        let position = camera_position + (planet.position - camera_position) * layer.parallax;
+        transform.translation = position.extend(transform.translation.z);
+    }
+}
+
+fn update_clouds(
+    mut clouds: Query<
+        (
+            &mut Transform,
+            &MeshMaterial2d<CloudsMaterial>,
+            &BackgroundLayer,
+        ),
+        Without<Camera2d>,
+    >,
+    camera: Query<(&Transform, &Projection), With<Camera2d>>,
+    windows: Query<&Window>,
+    mut materials: ResMut<Assets<CloudsMaterial>>,
+) {
+    let (camera_transform, projection) = camera.single().unwrap();
+    let view = view_size(projection, windows.single().unwrap());
+    let camera_position = camera_transform.translation.xy();
+
+    for (mut transform, material, layer) in &mut clouds {
+        transform.translation = camera_position.extend(transform.translation.z);
+        transform.scale = view.extend(1.0);
+
+        let material = materials.get_mut(&material.0).unwrap();
+        let offset = camera_position * (1.0 - layer.parallax);
+        material.params.x = offset.x;
+        material.params.y = offset.y;
+    }
+}
@@ -0,0 +1,5 @@
+// Headless authoritative server for networked matches
+fn main() {
+    #[cfg(not(target_arch = "wasm32"))]
+    bevy_workshop_rustweek_2025::run_server();
+}
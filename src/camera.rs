@@ -0,0 +1,305 @@
+use avian2d::prelude::*;
+use bevy::prelude::*;
+use bevy_enhanced_input::prelude::*;
+
+use crate::{
+    GameState,
+    game::{Asteroid, Explosion, Player, RespawnPrompt},
+    level::CurrentLevel,
+    settings::Settings,
+};
+
+const THREAT_RADIUS: f32 = 800.0;
+const MAX_SHAKE: f32 = 40.0;
+const TRAUMA_DECAY: f32 = 1.5;
+const HIT_STOP_SPEED: f32 = 0.05;
+
+pub fn camera_plugin(app: &mut App) {
+    app.add_input_context::<CameraController>()
+        .add_event::<Impact>()
+        .init_resource::<Trauma>()
+        .add_observer(zoom)
+        .add_systems(OnEnter(GameState::Game), reset_camera)
+        .add_systems(OnExit(GameState::Game), end_hit_stop)
+        .add_systems(
+            Update,
+            (
+                receive_impacts,
+                tick_hit_stop,
+                zoom_camera,
+                follow_players,
+                shake_camera,
+            )
+                .chain()
+                .run_if(in_state(GameState::Game)),
+        );
+}
+
+// Sent when something explodes or is hit; trauma is added to the camera shake,
+// hit-stop briefly slows down the game, in real seconds
+#[derive(Event)]
+pub struct Impact {
+    pub trauma: f32,
+    pub hit_stop: f32,
+}
+
+#[derive(Resource, Default)]
+struct Trauma(f32);
+
+#[derive(Resource)]
+struct HitStop(Timer);
+
+#[derive(Component)]
+struct CameraRig {
+    position: Vec2,
+    focus: Vec2,
+    velocity: Vec2,
+    manual_zoom: f32,
+}
+
+impl Default for CameraRig {
+    fn default() -> Self {
+        CameraRig {
+            position: Vec2::ZERO,
+            focus: Vec2::ZERO,
+            velocity: Vec2::ZERO,
+            manual_zoom: 1.0,
+        }
+    }
+}
+
+#[derive(InputContext)]
+struct CameraController;
+
+#[derive(Debug, InputAction)]
+#[input_action(output = f32)]
+struct Zoom;
+
+fn reset_camera(
+    mut commands: Commands,
+    mut camera: Query<(Entity, &mut Transform), With<Camera2d>>,
+    mut trauma: ResMut<Trauma>,
+) {
+    trauma.0 = 0.0;
+    for (entity, mut transform) in &mut camera {
+        transform.translation = Vec3::ZERO;
+
+        let mut actions = Actions::<CameraController>::default();
+        actions.bind::<Zoom>().to((
+            Bidirectional {
+                positive: KeyCode::Minus,
+                negative: KeyCode::Equal,
+            },
+            Input::mouse_wheel().with_modifiers((SwizzleAxis::YXZ, Scale::splat(-5.0))),
+        ));
+        commands
+            .entity(entity)
+            .insert((CameraRig::default(), actions));
+    }
+}
+
+fn receive_impacts(
+    mut commands: Commands,
+    mut impacts: EventReader<Impact>,
+    mut trauma: ResMut<Trauma>,
+    mut time: ResMut<Time<Virtual>>,
+    settings: Res<Settings>,
+) {
+    for impact in impacts.read() {
+        trauma.0 = (trauma.0 + impact.trauma).min(1.0);
+        if impact.hit_stop > 0.0 && !settings.reduced_motion {
+            time.set_relative_speed(HIT_STOP_SPEED * settings.assists.game_speed);
+            commands.insert_resource(HitStop(Timer::from_seconds(
+                impact.hit_stop,
+                TimerMode::Once,
+            )));
+        }
+    }
+}
+
+fn tick_hit_stop(
+    mut commands: Commands,
+    hit_stop: Option<ResMut<HitStop>>,
+    mut time: ResMut<Time<Virtual>>,
+    real_time: Res<Time<Real>>,
+    settings: Res<Settings>,
+) {
+    if let Some(mut hit_stop) = hit_stop
+        && hit_stop.0.tick(real_time.delta()).finished()
+    {
+        time.set_relative_speed(settings.assists.game_speed);
+        commands.remove_resource::<HitStop>();
+    }
+}
+
+fn end_hit_stop(mut commands: Commands, mut time: ResMut<Time<Virtual>>) {
+    time.set_relative_speed(1.0);
+    commands.remove_resource::<HitStop>();
+}
+
+fn zoom(trigger: Trigger<Fired<Zoom>>, mut rig: Query<&mut CameraRig>) -> Result {
+    let mut rig = rig.get_mut(trigger.target())?;
+    rig.manual_zoom = (rig.manual_zoom * (1.0 + trigger.value * 0.02)).clamp(0.5, 2.0);
+    Ok(())
+}
+
+// Zoom out when going fast or when there are many asteroids around, while
+// always keeping every ship on screen
+fn zoom_camera(
+    players: Query<(&Transform, &LinearVelocity), With<Player>>,
+    asteroids: Query<&Transform, With<Asteroid>>,
+    mut camera: Query<(&mut Projection, &CameraRig), With<Camera2d>>,
+    windows: Query<&Window>,
+    level: CurrentLevel,
+    time: Res<Time>,
+) {
+    let Ok((mut projection, rig)) = camera.single_mut() else {
+        return;
+    };
+    let Projection::Orthographic(orthographic) = projection.as_mut() else {
+        return;
+    };
+    let Some(bounds) = players
+        .iter()
+        .map(|(transform, _)| Rect::from_center_size(transform.translation.xy(), Vec2::ZERO))
+        .reduce(|a, b| a.union(b))
+    else {
+        return;
+    };
+    let settings = level.get().zoom;
+
+    let speed = players
+        .iter()
+        .map(|(_, velocity)| velocity.length())
+        .fold(0.0, f32::max);
+    let threats = asteroids
+        .iter()
+        .filter(|asteroid| {
+            players.iter().any(|(player, _)| {
+                player.translation.xy().distance(asteroid.translation.xy()) < THREAT_RADIUS
+            })
+        })
+        .count();
+    let dynamic = 1.0 + (speed / 300.0).min(1.0) * 0.5 + (threats as f32 / 8.0).min(1.0) * 0.5;
+
+    let fit = windows.single().map_or(0.0, |window| {
+        ((bounds.size() + 400.0) / window.size()).max_element()
+    });
+    let target = (dynamic * rig.manual_zoom)
+        .clamp(settings.min, settings.max)
+        .max(fit);
+    let scale = orthographic
+        .scale
+        .lerp(target, 1.0 - (-2.0 * time.delta_secs()).exp());
+    if (orthographic.scale - scale).abs() > 0.0001 {
+        orthographic.scale = scale;
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn follow_players(
+    players: Query<(&Transform, &LinearVelocity), (With<Player>, Without<Camera2d>)>,
+    explosions: Query<&Transform, (With<Explosion>, Without<Camera2d>)>,
+    prompts: Query<&RespawnPrompt>,
+    mut camera: Query<(&mut Transform, &Projection, &mut CameraRig), With<Camera2d>>,
+    windows: Query<&Window>,
+    level: CurrentLevel,
+    time: Res<Time>,
+) {
+    let Ok((mut camera_transform, projection, mut rig)) = camera.single_mut() else {
+        return;
+    };
+    let level = level.get();
+    let settings = level.camera;
+
+    let bounds = players
+        .iter()
+        .map(|(transform, _)| Rect::from_center_size(transform.translation.xy(), Vec2::ZERO))
+        .reduce(|a, b| a.union(b));
+
+    // When every ship is dead, hold on the explosion then pan to where the
+    // ship will respawn
+    let target = if let Some(bounds) = bounds {
+        let velocity = players.iter().map(|(_, velocity)| velocity.0).sum::<Vec2>()
+            / players.iter().len() as f32;
+        Some(bounds.center() + velocity * settings.look_ahead)
+    } else if !explosions.is_empty() {
+        Some(
+            explosions
+                .iter()
+                .map(|transform| transform.translation.xy())
+                .sum::<Vec2>()
+                / explosions.iter().len() as f32,
+        )
+    } else {
+        prompts.iter().find_map(|prompt| prompt.respawn_at)
+    };
+
+    if let Some(target) = target {
+        let offset = target - rig.focus;
+        if offset.length() > settings.deadzone {
+            rig.focus = target - offset.normalize() * settings.deadzone;
+        }
+    }
+
+    let focus = rig.focus;
+    let mut position = smooth_damp(
+        rig.position,
+        focus,
+        &mut rig.velocity,
+        settings.smoothing,
+        time.delta_secs(),
+    );
+
+    if let (Ok(window), Projection::Orthographic(orthographic)) = (windows.single(), projection) {
+        let half_level = Vec2::new(level.width as f32, level.height as f32) / 2.0;
+        let half_view = window.size() * orthographic.scale / 2.0;
+        let limit = (half_level - half_view).max(Vec2::ZERO);
+        position = position.clamp(-limit, limit);
+    }
+
+    rig.position = position;
+    camera_transform.translation = position.extend(0.0);
+}
+
+fn shake_camera(
+    mut camera: Query<(&mut Transform, &Projection), With<CameraRig>>,
+    mut trauma: ResMut<Trauma>,
+    settings: Res<Settings>,
+    time: Res<Time<Real>>,
+) {
+    let Ok((mut transform, projection)) = camera.single_mut() else {
+        return;
+    };
+    trauma.0 = (trauma.0 - TRAUMA_DECAY * time.delta_secs()).max(0.0);
+    if trauma.0 == 0.0 || settings.reduced_motion {
+        return;
+    }
+
+    // Shake grows with the square of the trauma so small hits stay subtle
+    let t = time.elapsed_secs();
+    let noise = Vec2::new(
+        (t * 37.0).sin() + (t * 23.3).sin() * 0.5,
+        (t * 31.0 + 1.3).sin() + (t * 19.7).sin() * 0.5,
+    ) / 1.5;
+    let scale = match projection {
+        Projection::Orthographic(orthographic) => orthographic.scale,
+        _ => 1.0,
+    };
+    transform.translation += (noise * MAX_SHAKE * scale * trauma.0 * trauma.0).extend(0.0);
+}
+
+// Critically damped spring, `frequency` is how fast it settles, in radians per second
+fn smooth_damp(
+    current: Vec2,
+    target: Vec2,
+    velocity: &mut Vec2,
+    frequency: f32,
+    delta: f32,
+) -> Vec2 {
+    let offset = current - target;
+    let decay = (-frequency * delta).exp();
+    let change = (*velocity + offset * frequency) * delta;
+    *velocity = (*velocity - change * frequency) * decay;
+    target + (offset + change) * decay
+}
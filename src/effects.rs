@@ -0,0 +1,61 @@
+use bevy::prelude::*;
+use bevy_enoki::prelude::*;
+
+use crate::{GameAssets, GameState};
+
+// Average colour of the asteroid sprite, debris is tinted with it
+const DEBRIS_COLOR: Color = Color::srgb(0.53, 0.39, 0.30);
+
+pub fn effects_plugin(app: &mut App) {
+    app.add_event::<Effect>()
+        .add_systems(Update, spawn_effects.run_if(in_state(GameState::Game)));
+}
+
+#[derive(Event)]
+pub enum Effect {
+    ShipExplosion(Vec2),
+    AsteroidDebris { position: Vec2, tint: Color },
+    LaserHit(Transform),
+}
+
+fn spawn_effects(
+    mut commands: Commands,
+    mut effects: EventReader<Effect>,
+    mut materials: ResMut<Assets<ColorParticle2dMaterial>>,
+    game_assets: Res<GameAssets>,
+) {
+    for effect in effects.read() {
+        let (material, particles, transform) = match effect {
+            Effect::ShipExplosion(position) => (
+                Handle::default(),
+                game_assets.ship_explosion_particles.clone(),
+                Transform::from_translation(position.extend(1.0)),
+            ),
+            Effect::AsteroidDebris { position, tint } => {
+                let (base, tint) = (DEBRIS_COLOR.to_linear(), tint.to_linear());
+                let color = LinearRgba::rgb(
+                    base.red * tint.red,
+                    base.green * tint.green,
+                    base.blue * tint.blue,
+                );
+                (
+                    materials.add(ColorParticle2dMaterial::new(color)),
+                    game_assets.asteroid_debris_particles.clone(),
+                    Transform::from_translation(position.extend(1.0)),
+                )
+            }
+            Effect::LaserHit(transform) => (
+                Handle::default(),
+                game_assets.laser_hit_particles.clone(),
+                transform.with_scale(Vec3::ONE),
+            ),
+        };
+        commands.spawn((
+            ParticleSpawner(material),
+            ParticleEffectHandle(particles),
+            OneShot::Despawn,
+            transform,
+            StateScoped(GameState::Game),
+        ));
+    }
+}
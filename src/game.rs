@@ -1,53 +1,286 @@
-use std::{f32::consts::TAU, time::Duration};
+use std::{f32::consts::TAU, ops::Range, time::Duration};
 
 use avian2d::prelude::*;
-use bevy::{audio::Volume, prelude::*};
+use bevy::{ecs::query::QueryData, prelude::*};
 use bevy_enhanced_input::prelude::*;
 use bevy_enoki::prelude::*;
 use rand::Rng;
 
-use crate::{AudioAssets, GameAssets, GameState, LoadedLevel, audio::AudioStart, level::Level};
+use crate::{
+    GameAssets, GameMode, GameState, LoadedLevel,
+    assists::{Assists, Toggle},
+    audio::AudioStart,
+    camera::Impact,
+    effects::Effect,
+    level::{CurrentLevel, Level, ShieldSettings},
+    localization::Locale,
+    pause::Pause,
+    settings::{Settings, key_name},
+    theme::{Theme, Themed},
+};
 
 pub fn game_plugin(app: &mut App) {
     app.add_input_context::<ShipController>()
+        .add_event::<RespawnRequest>()
+        .add_observer(bind_new_ship)
         .add_systems(OnEnter(GameState::Game), display_level)
         .add_systems(
             Update,
-            (tick_explosion, laser_range, has_won, follow_player, closest)
+            (
+                tick_explosion,
+                laser_range,
+                closest,
+                regenerate_shield,
+                display_shield,
+                tick_invulnerability,
+                (
+                    request_respawn.run_if(in_state(Pause::Running)),
+                    respawn_player,
+                )
+                    .chain(),
+                check_game_over,
+                tick_hyperspace,
+                display_asteroid_damage,
+                rebind_ships.run_if(resource_changed::<Settings>),
+            )
                 .run_if(in_state(GameState::Game)),
         );
 }
 
+// Gameplay rules, shared with the rollback simulation in net::simulation so
+// that network play follows the same game
+pub const SHIP_RADIUS: f32 = 40.0;
+pub const SHIP_ANGULAR_DAMPING: f32 = 5.0;
+// Added to the angular velocity every 60th of a second while turning
+pub const ROTATION_RATE: f32 = 0.2;
+// Added to the velocity every frame while thrusting
+pub const THRUST: f32 = 2.0;
+pub const MAX_SPEED: f32 = 300.0;
+pub const ASTEROID_RADIUS: f32 = 45.0;
+pub const ASTEROID_DENSITY: f32 = 2.0;
+pub const ASTEROID_SPEED: Range<f32> = 10.0..100.0;
+pub const ASTEROID_SIZE: Range<f32> = 0.6..1.4;
+// New asteroids keep this far from the ships at the center
+pub const ASTEROID_CLEARANCE: f32 = 200.0;
+pub const ASTEROID_IMPACT_THRESHOLD: f32 = 80.0;
+// Asteroids nearest to a ship but this far from all of them are sent back
+pub const NEAREST_PULL_DISTANCE: f32 = 1000.0;
+pub const NEAREST_PULL_SPEED: f32 = 100.0;
+pub const LASER_SPEED: f32 = 1000.0;
+pub const LASER_OFFSET: f32 = 40.0;
+pub const LASER_LIFETIME: f32 = 1.0;
+pub const FIRE_COOLDOWN: f32 = 0.5;
+// Only in versus, where lasers hit the other ship
+pub const LASER_SHIP_DAMAGE: f32 = 40.0;
+pub const SHIP_KILL_SCORE: u32 = 500;
+pub const EXPLOSION_DURATION: f32 = 1.0;
+pub const INVULNERABILITY_DURATION: f32 = 3.0;
+pub const HYPERSPACE_DURATION: f32 = 0.6;
+pub const SAFE_RESPAWN_DISTANCE: f32 = 300.0;
+
+pub fn asteroid_health(size: f32, toughness: f32) -> f32 {
+    (size * 3.0 * toughness).round().max(1.0)
+}
+
+pub fn asteroid_score(max_health: f32) -> u32 {
+    max_health as u32 * 10
+}
+
+// Damage done to both asteroids when they collide
+pub fn asteroid_impact_damage(impact_speed: f32) -> Option<f32> {
+    (impact_speed > ASTEROID_IMPACT_THRESHOLD)
+        .then(|| (impact_speed - ASTEROID_IMPACT_THRESHOLD) / 40.0)
+}
+
+pub fn ship_impact_damage(impact_speed: f32) -> f32 {
+    impact_speed * 0.25
+}
+
+pub fn spawn_point(player: usize, player_count: usize) -> Vec2 {
+    let offset = player as f32 - (player_count - 1) as f32 / 2.0;
+    Vec2::X * offset * 150.0
+}
+
+// Ships shrink into hyperspace and grow back out of it
+pub fn hyperspace_scale(progress: f32) -> f32 {
+    (progress * 2.0 - 1.0).abs().max(0.05)
+}
+
+// Whether a hit is absorbed, or destroys the ship
+pub fn absorb_hit(shield: Option<&mut Shield>, invulnerable: bool, damage: f32) -> bool {
+    if invulnerable {
+        return true;
+    }
+    let Some(shield) = shield else {
+        return false;
+    };
+    if shield.current > damage {
+        shield.current -= damage;
+        true
+    } else {
+        shield.current = 0.0;
+        false
+    }
+}
+
+// Closest point to `origin` inside the level that is clear of asteroids,
+// searched on square rings so that it doesn't depend on trigonometry
+pub fn find_safe_respawn(
+    origin: Vec2,
+    asteroids: impl Iterator<Item = Vec2> + Clone,
+    half_size: Vec2,
+) -> Option<Vec2> {
+    (0..6)
+        .flat_map(|ring: i32| {
+            (-ring..=ring)
+                .flat_map(move |x| (-ring..=ring).map(move |y| IVec2::new(x, y)))
+                .filter(move |step| step.abs().max_element() == ring)
+        })
+        .map(|step| origin + step.as_vec2() * 150.0)
+        .filter(|candidate| candidate.abs().cmple(half_size).all())
+        .find(|candidate| {
+            asteroids
+                .clone()
+                .all(|asteroid| asteroid.distance(*candidate) > SAFE_RESPAWN_DISTANCE)
+        })
+}
+
+#[derive(Component)]
+pub struct Player(pub usize);
+
+// When each ship last fired and jumped, its actions cool down separately
+#[derive(Component, Default)]
+struct Cooldowns {
+    last_fired: Option<Duration>,
+    last_jump: Option<Duration>,
+}
+
+// Position of the asteroid closest to a ship, if there is any
+#[derive(Component, Default)]
+pub struct NearestAsteroid(pub Option<Vec2>);
+
+#[derive(Component, Clone, Copy)]
+pub struct Shield {
+    pub current: f32,
+    pub capacity: f32,
+    regeneration: f32,
+}
+
+impl From<ShieldSettings> for Shield {
+    fn from(settings: ShieldSettings) -> Self {
+        Shield {
+            current: settings.capacity,
+            capacity: settings.capacity,
+            regeneration: settings.regeneration,
+        }
+    }
+}
+
+impl Shield {
+    // From 0 when depleted to 1 when full
+    pub fn fraction(&self) -> f32 {
+        if self.capacity > 0.0 {
+            self.current / self.capacity
+        } else {
+            0.0
+        }
+    }
+
+    pub fn regenerate(&mut self, delta: f32) {
+        self.current = (self.current + self.regeneration * delta).min(self.capacity);
+    }
+}
+
 #[derive(Component)]
-struct Player;
+struct Invulnerable(Timer);
 
 #[derive(Component)]
 pub struct Asteroid;
 
 #[derive(Component)]
-struct Explosion(Timer);
+struct Health {
+    current: f32,
+    max: f32,
+}
+
+#[derive(Component)]
+pub struct Explosion {
+    timer: Timer,
+    player: usize,
+}
 
 #[derive(Resource)]
-pub struct LivesRemaining(pub u32);
+pub struct Pilots(pub Vec<Pilot>);
+
+pub struct Pilot {
+    pub lives: u32,
+    pub score: u32,
+    pub eliminated: bool,
+}
+
+impl Pilots {
+    pub fn total_score(&self) -> u32 {
+        self.0.iter().map(|pilot| pilot.score).sum()
+    }
+}
+
+#[derive(Resource)]
+pub struct Winner(pub usize);
+
+#[derive(Resource)]
+pub struct Ammo(pub u32);
 
 fn display_level(
     mut commands: Commands,
     game_assets: Res<GameAssets>,
     loaded_level: Res<LoadedLevel>,
     levels: Res<Assets<Level>>,
-    audio_assets: Res<AudioAssets>,
+    game_mode: Res<GameMode>,
+    theme: Res<Theme>,
 ) {
     let level = levels.get(&loaded_level.level).unwrap();
 
-    commands.insert_resource(LivesRemaining(level.lives - 1));
-
+    commands.insert_resource(Pilots(
+        (0..game_mode.player_count())
+            .map(|_| Pilot {
+                lives: level.lives - 1,
+                score: 0,
+                eliminated: false,
+            })
+            .collect(),
+    ));
+    commands.remove_resource::<Winner>();
+    match level.ammo {
+        Some(ammo) => commands.insert_resource(Ammo(ammo)),
+        None => commands.remove_resource::<Ammo>(),
+    }
     commands.spawn((
-        AudioPlayer::<AudioSource>(audio_assets.game_loop.clone()),
-        PlaybackSettings::LOOP.with_volume(Volume::Decibels(-5.0)),
+        Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            flex_direction: FlexDirection::Column,
+            ..default()
+        },
+        RespawnPrompts,
         StateScoped(GameState::Game),
     ));
 
-    spawn_player(&mut commands, game_assets.as_ref(), Vec2::ZERO);
+    for player in 0..game_mode.player_count() {
+        spawn_player(
+            &mut commands,
+            game_assets.as_ref(),
+            &theme,
+            level,
+            player,
+            spawn_point(player, game_mode.player_count()),
+        );
+    }
+
+    if *game_mode == GameMode::Endless {
+        return;
+    }
 
     let mut rng = rand::thread_rng();
 
@@ -56,7 +289,7 @@ fn display_level(
             let x = rng.gen_range(-(level.width as f32) / 2.0..(level.width as f32) / 2.0);
             let y = rng.gen_range(-(level.height as f32) / 2.0..(level.height as f32) / 2.0);
 
-            if Vec2::new(x, y).distance(Vec2::ZERO) < 200.0 {
+            if Vec2::new(x, y).distance(Vec2::ZERO) < ASTEROID_CLEARANCE {
                 return None;
             }
 
@@ -65,89 +298,345 @@ fn display_level(
         .take(level.asteroids as usize)
         .collect::<Vec<_>>()
     {
-        commands.spawn((
+        spawn_asteroid(
+            &mut commands,
+            game_assets.as_ref(),
+            Vec2::new(x, y),
+            Vec2::from_angle(rng.gen_range(0.0..TAU)) * rng.gen_range(ASTEROID_SPEED),
+            rng.gen_range(ASTEROID_SIZE),
+            1.0,
+        );
+    }
+}
+
+pub fn spawn_asteroid(
+    commands: &mut Commands,
+    game_assets: &GameAssets,
+    position: Vec2,
+    velocity: Vec2,
+    size: f32,
+    toughness: f32,
+) {
+    let health = asteroid_health(size, toughness);
+    commands
+        .spawn((
             Sprite::from_image(game_assets.asteroid.clone()),
-            Transform::from_xyz(x, y, 0.0),
+            Transform::from_translation(position.extend(0.0)).with_scale(Vec3::splat(size)),
             RigidBody::Dynamic,
-            Collider::circle(45.0),
-            LinearVelocity(Vec2::from_angle(rng.gen_range(0.0..TAU)) * rng.gen_range(10.0..100.0)),
-            AngularVelocity(rng.gen_range(-1.5..1.5)),
+            Collider::circle(ASTEROID_RADIUS),
+            ColliderDensity(ASTEROID_DENSITY),
+            LinearVelocity(velocity),
+            AngularVelocity(rand::thread_rng().gen_range(-1.5..1.5)),
             Asteroid,
+            Health {
+                current: health,
+                max: health,
+            },
+            CollisionEventsEnabled,
             StateScoped(GameState::Game),
-        ));
-    }
+            children![(
+                Sprite::from_image(game_assets.asteroid_cracks[0].clone()),
+                Transform::from_xyz(0.0, 0.0, 0.1),
+                Visibility::Hidden,
+            )],
+        ))
+        .observe(asteroid_impact);
 }
 
 fn tick_explosion(
     mut commands: Commands,
     mut explosions: Query<(Entity, &mut Explosion, &Transform)>,
+    prompts: Query<Entity, With<RespawnPrompts>>,
     time: Res<Time>,
+    mut pilots: ResMut<Pilots>,
+    settings: Res<Settings>,
+    game_mode: Res<GameMode>,
+) -> Result {
+    // Versus always ends with a winner
+    let infinite_lives = settings.assists.infinite_lives && *game_mode != GameMode::Versus;
+
+    for (entity, mut explosion, transform) in explosions.iter_mut() {
+        if explosion.timer.tick(time.delta()).just_finished() {
+            commands.entity(entity).despawn();
+            let pilot = &mut pilots.0[explosion.player];
+            if pilot.lives == 0 && !infinite_lives {
+                pilot.eliminated = true;
+            } else {
+                if !infinite_lives {
+                    pilot.lives -= 1;
+                }
+                commands.entity(prompts.single()?).with_child((
+                    Text::default(),
+                    TextFont {
+                        font_size: 50.0,
+                        ..default()
+                    },
+                    Themed::Hud,
+                    RespawnPrompt {
+                        player: explosion.player,
+                        position: transform.translation.xy(),
+                        respawn_at: None,
+                    },
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn check_game_over(
+    mut commands: Commands,
+    pilots: Res<Pilots>,
+    game_mode: Res<GameMode>,
     mut next_state: ResMut<NextState<GameState>>,
-    mut lives_remaining: ResMut<LivesRemaining>,
-    game_assets: Res<GameAssets>,
     mut audio: EventWriter<AudioStart>,
 ) {
-    for (entity, mut timer, transform) in explosions.iter_mut() {
-        if timer.0.tick(time.delta()).just_finished() {
-            if lives_remaining.0 == 0 {
-                next_state.set(GameState::StartMenu);
-                audio.write(AudioStart::Lose);
-            } else {
-                commands.entity(entity).despawn();
-                lives_remaining.0 -= 1;
-                spawn_player(
-                    &mut commands,
-                    game_assets.as_ref(),
-                    transform.translation.xy(),
-                );
-            }
+    if !pilots.is_changed() {
+        return;
+    }
+
+    if *game_mode == GameMode::Versus {
+        if let Some(loser) = pilots.0.iter().position(|pilot| pilot.eliminated) {
+            commands.insert_resource(Winner(1 - loser));
+            next_state.set(GameState::Won);
+            audio.write(AudioStart::new("win"));
         }
+    } else if pilots.0.iter().all(|pilot| pilot.eliminated) {
+        next_state.set(GameState::StartMenu);
+        audio.write(AudioStart::new("lose"));
     }
 }
 
-fn spawn_player(commands: &mut Commands, game_assets: &GameAssets, position: Vec2) {
-    let mut actions = Actions::<ShipController>::default();
+#[derive(Component)]
+struct RespawnPrompts;
 
-    actions.bind::<Rotate>().to(Bidirectional {
-        positive: KeyCode::KeyA,
-        negative: KeyCode::KeyD,
-    });
-    actions.bind::<Thrust>().to(KeyCode::KeyW);
-    actions.bind::<FireLaser>().to(KeyCode::Space);
+#[derive(Component)]
+pub struct RespawnPrompt {
+    player: usize,
+    position: Vec2,
+    pub respawn_at: Option<Vec2>,
+}
 
-    commands
-        .spawn((
-            Sprite::from_image(game_assets.player_ship.clone()),
-            RigidBody::Dynamic,
-            Collider::circle(40.0),
-            AngularDamping(5.0),
-            Player,
-            Transform::from_translation(position.extend(0.0)),
-            CollisionEventsEnabled,
-            StateScoped(GameState::Game),
-            children![
-                (
-                    Sprite::from_image(game_assets.jets.clone()),
-                    Transform::from_xyz(0.0, -40.0, -1.0),
-                    Visibility::Hidden,
-                ),
-                (
-                    ParticleSpawner::default(),
-                    ParticleSpawnerState {
-                        active: false,
-                        ..default()
-                    },
-                    ParticleEffectHandle(game_assets.jet_particles.clone()),
-                    Transform::from_xyz(0.0, -40.0, 0.0),
-                )
-            ],
-            actions,
-        ))
+#[derive(Event)]
+pub struct RespawnRequest(pub usize);
+
+fn request_respawn(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    prompts: Query<&RespawnPrompt>,
+    mut requests: EventWriter<RespawnRequest>,
+    settings: Res<Settings>,
+) {
+    for prompt in &prompts {
+        if keyboard.just_pressed(settings.controls[prompt.player].fire) {
+            requests.write(RespawnRequest(prompt.player));
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn respawn_player(
+    mut commands: Commands,
+    mut requests: EventReader<RespawnRequest>,
+    asteroids: Query<&Transform, With<Asteroid>>,
+    mut prompts: Query<(Entity, &mut RespawnPrompt, &mut Text)>,
+    game_assets: Res<GameAssets>,
+    level: CurrentLevel,
+    game_mode: Res<GameMode>,
+    settings: Res<Settings>,
+    theme: Res<Theme>,
+    locale: Locale,
+) {
+    let level = level.get();
+    let requested = requests.read().map(|request| request.0).collect::<Vec<_>>();
+
+    for (entity, mut prompt, mut text) in &mut prompts {
+        let fire = key_name(settings.controls[prompt.player].fire);
+        let label = if game_mode.player_count() > 1 {
+            locale.format(
+                "respawn-player",
+                &[("player", (prompt.player + 1).to_string())],
+            )
+        } else {
+            String::new()
+        };
+
+        prompt.respawn_at = find_safe_respawn(
+            prompt.position,
+            asteroids.iter().map(|asteroid| asteroid.translation.xy()),
+            Vec2::new(level.width as f32, level.height as f32) / 2.0,
+        );
+        let Some(position) = prompt.respawn_at else {
+            text.0 = label + &locale.get("respawn-waiting");
+            continue;
+        };
+        text.0 = label + &locale.format("respawn-press", &[("key", fire.to_string())]);
+
+        if requested.contains(&prompt.player) {
+            let player = spawn_player(
+                &mut commands,
+                game_assets.as_ref(),
+                &theme,
+                level,
+                prompt.player,
+                position,
+            );
+            commands
+                .entity(player)
+                .insert(Invulnerable(Timer::from_seconds(
+                    INVULNERABILITY_DURATION,
+                    TimerMode::Once,
+                )));
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Controls {
+    pub rotate_left: KeyCode,
+    pub rotate_right: KeyCode,
+    pub thrust: KeyCode,
+    pub fire: KeyCode,
+    pub hyperspace: KeyCode,
+}
+
+impl Controls {
+    pub fn bindings(&self) -> [(&'static str, KeyCode); 5] {
+        [
+            ("rotate_left", self.rotate_left),
+            ("rotate_right", self.rotate_right),
+            ("thrust", self.thrust),
+            ("fire", self.fire),
+            ("hyperspace", self.hyperspace),
+        ]
+    }
+
+    pub fn bindings_mut(&mut self) -> [(&'static str, &mut KeyCode); 5] {
+        [
+            ("rotate_left", &mut self.rotate_left),
+            ("rotate_right", &mut self.rotate_right),
+            ("thrust", &mut self.thrust),
+            ("fire", &mut self.fire),
+            ("hyperspace", &mut self.hyperspace),
+        ]
+    }
+}
+
+pub const DEFAULT_CONTROLS: [Controls; 2] = [
+    Controls {
+        rotate_left: KeyCode::KeyA,
+        rotate_right: KeyCode::KeyD,
+        thrust: KeyCode::KeyW,
+        fire: KeyCode::Space,
+        hyperspace: KeyCode::KeyS,
+    },
+    Controls {
+        rotate_left: KeyCode::ArrowLeft,
+        rotate_right: KeyCode::ArrowRight,
+        thrust: KeyCode::ArrowUp,
+        fire: KeyCode::Enter,
+        hyperspace: KeyCode::ArrowDown,
+    },
+];
+
+fn spawn_player(
+    commands: &mut Commands,
+    game_assets: &GameAssets,
+    theme: &Theme,
+    level: &Level,
+    id: usize,
+    position: Vec2,
+) -> Entity {
+    let mut player = commands.spawn((
+        Sprite {
+            image: game_assets.player_ship.clone(),
+            color: theme.players[id],
+            ..default()
+        },
+        RigidBody::Dynamic,
+        Collider::circle(SHIP_RADIUS),
+        AngularDamping(SHIP_ANGULAR_DAMPING),
+        Player(id),
+        NearestAsteroid::default(),
+        Cooldowns::default(),
+        Transform::from_translation(position.extend(0.0)),
+        CollisionEventsEnabled,
+        StateScoped(GameState::Game),
+        children![
+            (
+                Sprite::from_image(game_assets.jets.clone()),
+                Transform::from_xyz(0.0, -40.0, -1.0),
+                Visibility::Hidden,
+            ),
+            (
+                ParticleSpawner::default(),
+                ParticleSpawnerState {
+                    active: false,
+                    ..default()
+                },
+                ParticleEffectHandle(game_assets.jet_particles.clone()),
+                Transform::from_xyz(0.0, -40.0, 0.0),
+            )
+        ],
+    ));
+    player
         .observe(rotate)
         .observe(thrust)
         .observe(thrust_stop)
         .observe(fire_laser)
+        .observe(hyperspace)
         .observe(asteroid_collision);
+
+    if let Some(shield) = level.shield {
+        player.insert(Shield::from(shield));
+    }
+
+    player.id()
+}
+
+fn ship_actions(controls: &Controls, assists: &Assists) -> Actions<ShipController> {
+    let mut actions = Actions::<ShipController>::default();
+
+    actions.bind::<Rotate>().to(Bidirectional {
+        positive: controls.rotate_left,
+        negative: controls.rotate_right,
+    });
+    let thrust = actions.bind::<Thrust>().to(controls.thrust);
+    if assists.toggle_thrust {
+        thrust.with_conditions(Toggle::default());
+    }
+    actions.bind::<FireLaser>().to(controls.fire);
+    actions.bind::<Hyperspace>().to(controls.hyperspace);
+
+    actions
+}
+
+fn bind_new_ship(
+    trigger: Trigger<OnAdd, Player>,
+    mut commands: Commands,
+    players: Query<&Player>,
+    settings: Res<Settings>,
+) -> Result {
+    let player = players.get(trigger.target())?;
+    commands.entity(trigger.target()).insert(ship_actions(
+        &settings.controls[player.0],
+        &settings.assists,
+    ));
+    Ok(())
+}
+
+// Bindings can't be removed from actions, so they are replaced when the controls change
+fn rebind_ships(
+    mut commands: Commands,
+    players: Query<(Entity, &Player)>,
+    settings: Res<Settings>,
+) {
+    for (entity, player) in &players {
+        commands.entity(entity).insert(ship_actions(
+            &settings.controls[player.0],
+            &settings.assists,
+        ));
+    }
 }
 
 #[derive(InputContext)]
@@ -155,24 +644,26 @@ struct ShipController;
 
 #[derive(Debug, InputAction)]
 #[input_action(output = f32)]
-struct Rotate;
+pub struct Rotate;
 
 #[derive(Debug, InputAction)]
 #[input_action(output = bool)]
-struct Thrust;
+pub struct Thrust;
 
 #[derive(Debug, InputAction)]
 #[input_action(output = bool)]
-struct FireLaser;
+pub struct FireLaser;
+
+#[derive(Debug, InputAction)]
+#[input_action(output = bool)]
+pub struct Hyperspace;
 
 fn rotate(
     trigger: Trigger<Fired<Rotate>>,
     mut player: Query<&mut AngularVelocity>,
     time: Res<Time>,
 ) -> Result {
-    let fixed_rate = 0.2;
-    let delta = time.delta().as_secs_f32();
-    let rate = fixed_rate / (1.0 / (60.0 * delta));
+    let rate = ROTATION_RATE * 60.0 * time.delta_secs();
     let mut angular_velocity = player.get_mut(trigger.target())?;
     angular_velocity.0 += trigger.value.signum() * rate;
 
@@ -186,8 +677,8 @@ fn thrust(
     mut particle_state: Query<&mut ParticleSpawnerState>,
 ) -> Result {
     let (transform, mut linear_velocity, children) = player.get_mut(trigger.target())?;
-    linear_velocity.0 += transform.local_y().xy() * 2.0;
-    linear_velocity.0 = linear_velocity.0.clamp_length_max(300.0);
+    linear_velocity.0 += transform.local_y().xy() * THRUST;
+    linear_velocity.0 = linear_velocity.0.clamp_length_max(MAX_SPEED);
 
     visibility
         .get_mut(children[0])?
@@ -222,72 +713,270 @@ fn thrust_stop(
     Ok(())
 }
 
+#[derive(QueryData)]
+#[query_data(mutable)]
+struct Ship {
+    player: &'static Player,
+    transform: &'static Transform,
+    velocity: &'static LinearVelocity,
+    shield: Option<&'static mut Shield>,
+    invulnerable: Has<Invulnerable>,
+}
+
+impl ShipItem<'_> {
+    fn absorb_hit(&mut self, damage: f32) -> bool {
+        absorb_hit(self.shield.as_deref_mut(), self.invulnerable, damage)
+    }
+}
+
 fn asteroid_collision(
     collision: Trigger<OnCollisionStart>,
-    is_asteroid: Query<(), With<Asteroid>>,
-    player: Query<&Transform>,
+    asteroids: Query<(&LinearVelocity, &Transform, &Sprite), With<Asteroid>>,
+    mut ships: Query<Ship>,
     mut commands: Commands,
-    game_assets: Res<GameAssets>,
     mut audio: EventWriter<AudioStart>,
 ) -> Result {
-    if is_asteroid.get(collision.collider).is_ok() {
-        let transform = player.get(collision.target())?;
-        commands.spawn((
-            Sprite::from_image(game_assets.explosion.clone()),
-            (*transform).with_scale(Vec3::splat(0.2)),
-            Explosion(Timer::from_seconds(1.0, TimerMode::Once)),
-            StateScoped(GameState::Game),
-        ));
-        commands.entity(collision.target()).despawn();
-        commands.entity(collision.collider).despawn();
-        audio.write(AudioStart::ShipExplosion);
+    let Ok((asteroid_velocity, asteroid_transform, asteroid_sprite)) =
+        asteroids.get(collision.collider)
+    else {
+        return Ok(());
+    };
+    let mut ship = ships.get_mut(collision.target())?;
+
+    let impact_speed = (ship.velocity.0 - asteroid_velocity.0).length();
+    if ship.absorb_hit(ship_impact_damage(impact_speed)) {
+        commands.send_event(Impact {
+            trauma: (impact_speed / 400.0).min(0.5),
+            hit_stop: 0.0,
+        });
+        return Ok(());
     }
+
+    destroy_ship(
+        &mut commands,
+        &mut audio,
+        collision.target(),
+        ship.player.0,
+        ship.transform,
+    );
+    commands.entity(collision.collider).despawn();
+    commands.send_event(Effect::AsteroidDebris {
+        position: asteroid_transform.translation.xy(),
+        tint: asteroid_sprite.color,
+    });
     Ok(())
 }
 
+fn destroy_ship(
+    commands: &mut Commands,
+    audio: &mut EventWriter<AudioStart>,
+    ship: Entity,
+    player: usize,
+    transform: &Transform,
+) {
+    commands.spawn((
+        // Network clients draw the explosion sprite at this scale
+        transform.with_scale(Vec3::splat(0.2)),
+        Explosion {
+            timer: Timer::from_seconds(EXPLOSION_DURATION, TimerMode::Once),
+            player,
+        },
+        StateScoped(GameState::Game),
+    ));
+    commands.entity(ship).despawn();
+    commands.send_event(Effect::ShipExplosion(transform.translation.xy()));
+    commands.send_event(Impact {
+        trauma: 0.7,
+        hit_stop: 0.08,
+    });
+    audio.write(AudioStart::at("ship_explosion", transform.translation.xy()));
+}
+
 #[derive(Component)]
-struct Laser(Timer);
+struct HyperspaceJump {
+    timer: Timer,
+    arrived: bool,
+}
+
+fn hyperspace(
+    trigger: Trigger<Started<Hyperspace>>,
+    mut player: Query<(&Transform, &mut Cooldowns), Without<HyperspaceJump>>,
+    mut commands: Commands,
+    time: Res<Time>,
+    level: CurrentLevel,
+    mut audio: EventWriter<AudioStart>,
+) {
+    let Ok((transform, mut cooldowns)) = player.get_mut(trigger.target()) else {
+        return;
+    };
+    let cooldown = Duration::from_secs_f32(level.get().hyperspace.cooldown);
+    if cooldowns
+        .last_jump
+        .is_some_and(|last_jump| time.elapsed() < last_jump + cooldown)
+    {
+        return;
+    }
+
+    commands.entity(trigger.target()).insert((
+        HyperspaceJump {
+            timer: Timer::from_seconds(HYPERSPACE_DURATION, TimerMode::Once),
+            arrived: false,
+        },
+        RigidBodyDisabled,
+        ColliderDisabled,
+    ));
+    audio.write(AudioStart::at("hyperspace", transform.translation.xy()));
 
+    cooldowns.last_jump = Some(time.elapsed());
+}
+
+fn tick_hyperspace(
+    mut commands: Commands,
+    mut jumping: Query<(
+        Entity,
+        &Player,
+        &mut HyperspaceJump,
+        &mut Transform,
+        &mut LinearVelocity,
+    )>,
+    time: Res<Time>,
+    level: CurrentLevel,
+    mut audio: EventWriter<AudioStart>,
+) {
+    let level = level.get();
+    let mut rng = rand::thread_rng();
+
+    for (entity, player, mut jump, mut transform, mut linear_velocity) in &mut jumping {
+        jump.timer.tick(time.delta());
+        let progress = jump.timer.fraction();
+
+        if !jump.arrived && progress >= 0.5 {
+            jump.arrived = true;
+            if rng.r#gen::<f32>() < level.hyperspace.risk {
+                destroy_ship(&mut commands, &mut audio, entity, player.0, &transform);
+                continue;
+            }
+            transform.translation.x =
+                rng.gen_range(-(level.width as f32) / 2.0..(level.width as f32) / 2.0);
+            transform.translation.y =
+                rng.gen_range(-(level.height as f32) / 2.0..(level.height as f32) / 2.0);
+            linear_velocity.0 = Vec2::ZERO;
+        }
+
+        if jump.timer.finished() {
+            transform.scale = Vec3::ONE;
+            commands
+                .entity(entity)
+                .remove::<(HyperspaceJump, RigidBodyDisabled, ColliderDisabled)>();
+        } else {
+            transform.scale = Vec3::splat(hyperspace_scale(progress));
+        }
+    }
+}
+
+fn regenerate_shield(mut shields: Query<&mut Shield>, time: Res<Time>) {
+    for mut shield in &mut shields {
+        shield.regenerate(time.delta_secs());
+    }
+}
+
+fn display_shield(
+    player: Query<(&Transform, &Shield), With<Player>>,
+    mut gizmos: Gizmos,
+    theme: Res<Theme>,
+) {
+    for (transform, shield) in &player {
+        if shield.current > 0.0 {
+            gizmos.circle_2d(
+                transform.translation.xy(),
+                55.0,
+                theme.shield.with_alpha(shield.fraction()),
+            );
+        }
+    }
+}
+
+fn tick_invulnerability(
+    mut commands: Commands,
+    mut player: Query<(Entity, &mut Invulnerable, &mut Visibility)>,
+    time: Res<Time>,
+) {
+    for (entity, mut invulnerable, mut visibility) in &mut player {
+        if invulnerable.0.tick(time.delta()).finished() {
+            commands.entity(entity).remove::<Invulnerable>();
+            *visibility = Visibility::Inherited;
+        } else if ((invulnerable.0.elapsed_secs() * 8.0) as u32).is_multiple_of(2) {
+            *visibility = Visibility::Hidden;
+        } else {
+            *visibility = Visibility::Inherited;
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Laser {
+    timer: Timer,
+    fired_by: usize,
+}
+
+#[allow(clippy::too_many_arguments)]
 fn fire_laser(
     trigger: Trigger<Fired<FireLaser>>,
-    player: Query<&Transform>,
+    mut player: Query<(&Player, &Transform, &mut Cooldowns), Without<HyperspaceJump>>,
     mut commands: Commands,
     time: Res<Time>,
-    mut last_fired: Local<Duration>,
+    mut ammo: Option<ResMut<Ammo>>,
     game_assets: Res<GameAssets>,
     mut audio: EventWriter<AudioStart>,
+    theme: Res<Theme>,
 ) -> Result {
-    let mut transform = *player.get(trigger.target())?;
-    transform.translation += transform.local_y() * 40.0;
+    let Ok((player, &transform, mut cooldowns)) = player.get_mut(trigger.target()) else {
+        return Ok(());
+    };
+    let mut transform = transform;
+    transform.translation += transform.local_y() * LASER_OFFSET;
     transform.scale = Vec3::ONE / 2.0;
 
-    if time.elapsed() > *last_fired + Duration::from_secs_f32(0.5) {
+    if ammo.as_ref().is_some_and(|ammo| ammo.0 == 0) {
+        return Ok(());
+    }
+
+    if cooldowns.last_fired.is_none_or(|last_fired| {
+        time.elapsed() > last_fired + Duration::from_secs_f32(FIRE_COOLDOWN)
+    }) {
+        if let Some(ammo) = ammo.as_mut() {
+            ammo.0 -= 1;
+        }
         commands
             .spawn((
                 Sprite {
                     image: game_assets.laser.clone(),
-                    color: Color::srgb(5.0, 1.0, 1.0),
+                    color: theme.laser,
                     ..default()
                 },
                 transform,
                 RigidBody::Dynamic,
                 Collider::rectangle(4.0, 15.0),
-                LinearVelocity(transform.local_y().xy() * 1000.0),
-                Laser(Timer::from_seconds(1.0, TimerMode::Once)),
+                LinearVelocity(transform.local_y().xy() * LASER_SPEED),
+                Laser {
+                    timer: Timer::from_seconds(LASER_LIFETIME, TimerMode::Once),
+                    fired_by: player.0,
+                },
                 CollisionEventsEnabled,
                 StateScoped(GameState::Game),
             ))
-            .observe(laser_attack);
-        audio.write(AudioStart::Laser);
+            .observe(laser_attack)
+            .observe(laser_hit_ship);
+        audio.write(AudioStart::at("laser", transform.translation.xy()));
 
-        *last_fired = time.elapsed();
+        cooldowns.last_fired = Some(time.elapsed());
     }
     Ok(())
 }
 
 fn laser_range(mut commands: Commands, mut lasers: Query<(Entity, &mut Laser)>, time: Res<Time>) {
     for (entity, mut laser) in &mut lasers {
-        if laser.0.tick(time.delta()).just_finished() {
+        if laser.timer.tick(time.delta()).just_finished() {
             commands.entity(entity).despawn();
         }
     }
@@ -295,75 +984,173 @@ fn laser_range(mut commands: Commands, mut lasers: Query<(Entity, &mut Laser)>,
 
 fn laser_attack(
     collision: Trigger<OnCollisionStart>,
-    is_asteroid: Query<(), With<Asteroid>>,
+    lasers: Query<(&Laser, &Transform)>,
+    mut asteroids: Query<(&mut Health, &Transform, &Sprite), With<Asteroid>>,
     mut commands: Commands,
+    mut pilots: ResMut<Pilots>,
     mut audio: EventWriter<AudioStart>,
-) {
-    if is_asteroid.get(collision.collider).is_ok() {
-        commands.entity(collision.collider).despawn();
+    mut impacts: EventWriter<Impact>,
+) -> Result {
+    let (laser, laser_transform) = lasers.get(collision.target())?;
+    if let Ok((mut health, transform, sprite)) = asteroids.get_mut(collision.collider) {
         commands.entity(collision.target()).despawn();
-        audio.write(AudioStart::AsteroidExplosion);
+        commands.send_event(Effect::LaserHit(*laser_transform));
+        health.current -= 1.0;
+        if health.current <= 0.0 {
+            commands.entity(collision.collider).despawn();
+            commands.send_event(Effect::AsteroidDebris {
+                position: transform.translation.xy(),
+                tint: sprite.color,
+            });
+            pilots.0[laser.fired_by].score += asteroid_score(health.max);
+            audio.write(AudioStart::at(
+                "asteroid_explosion",
+                transform.translation.xy(),
+            ));
+            impacts.write(Impact {
+                trauma: 0.25 * transform.scale.x,
+                hit_stop: 0.03 * transform.scale.x,
+            });
+        } else {
+            impacts.write(Impact {
+                trauma: 0.05,
+                hit_stop: 0.0,
+            });
+        }
     }
+    Ok(())
 }
 
-fn has_won(
-    asteroids: Query<(), With<Asteroid>>,
-    mut next_state: ResMut<NextState<GameState>>,
+fn laser_hit_ship(
+    collision: Trigger<OnCollisionStart>,
+    lasers: Query<(&Laser, &Transform)>,
+    mut ships: Query<Ship>,
+    mut commands: Commands,
+    game_mode: Res<GameMode>,
+    mut pilots: ResMut<Pilots>,
     mut audio: EventWriter<AudioStart>,
-) {
-    if asteroids.is_empty() {
-        next_state.set(GameState::Won);
-        audio.write(AudioStart::Win);
+) -> Result {
+    let (laser, laser_transform) = lasers.get(collision.target())?;
+    let Ok(mut ship) = ships.get_mut(collision.collider) else {
+        return Ok(());
+    };
+    if ship.player.0 == laser.fired_by {
+        return Ok(());
     }
+    commands.entity(collision.target()).despawn();
+    commands.send_event(Effect::LaserHit(*laser_transform));
+    if *game_mode != GameMode::Versus || ship.absorb_hit(LASER_SHIP_DAMAGE) {
+        return Ok(());
+    }
+
+    pilots.0[laser.fired_by].score += SHIP_KILL_SCORE;
+    destroy_ship(
+        &mut commands,
+        &mut audio,
+        collision.collider,
+        ship.player.0,
+        ship.transform,
+    );
+    Ok(())
 }
 
-fn follow_player(
-    player: Query<&Transform, With<Player>>,
-    mut camera: Query<&mut Transform, (With<Camera2d>, Without<Player>)>,
+fn asteroid_impact(
+    collision: Trigger<OnCollisionStart>,
+    mut asteroids: Query<(&LinearVelocity, &mut Health, &Transform, &Sprite), With<Asteroid>>,
+    mut commands: Commands,
+    mut audio: EventWriter<AudioStart>,
 ) {
-    let Ok(player_transform) = player.single() else {
-        return;
-    };
-    let Ok(mut camera_transform) = camera.single_mut() else {
+    let Ok(
+        [
+            (velocity, mut health, transform, sprite),
+            (other_velocity, ..),
+        ],
+    ) = asteroids.get_many_mut([collision.target(), collision.collider])
+    else {
         return;
     };
-    camera_transform.translation = player_transform.translation;
+
+    let impact_speed = (velocity.0 - other_velocity.0).length();
+    if let Some(damage) = asteroid_impact_damage(impact_speed) {
+        health.current -= damage;
+        if health.current <= 0.0 {
+            commands.entity(collision.target()).despawn();
+            commands.send_event(Effect::AsteroidDebris {
+                position: transform.translation.xy(),
+                tint: sprite.color,
+            });
+            audio.write(AudioStart::at(
+                "asteroid_explosion",
+                transform.translation.xy(),
+            ));
+        }
+    }
+}
+
+fn display_asteroid_damage(
+    asteroids: Query<(&Health, &Children), Changed<Health>>,
+    mut cracks: Query<(&mut Sprite, &mut Visibility)>,
+    game_assets: Res<GameAssets>,
+) {
+    for (health, children) in &asteroids {
+        let Ok((mut sprite, mut visibility)) = cracks.get_mut(children[0]) else {
+            continue;
+        };
+        let fraction = health.current / health.max;
+        if fraction > 0.66 {
+            *visibility = Visibility::Hidden;
+        } else {
+            *visibility = Visibility::Inherited;
+            let stage = if fraction > 0.33 { 0 } else { 1 };
+            sprite.image = game_assets.asteroid_cracks[stage].clone();
+        }
+    }
 }
 
 fn closest(
     asteroids: Query<(Entity, &Transform), With<Asteroid>>,
-    player: Query<&Transform, With<Player>>,
+    mut players: Query<(&Transform, &mut NearestAsteroid), With<Player>>,
     mut gizmos: Gizmos,
     mut commands: Commands,
+    theme: Res<Theme>,
 ) {
-    let Ok(player_transform) = player.single() else {
-        return;
-    };
-    let player_position = player_transform.translation.xy();
-    let Some((entity, nearest)) = asteroids.iter().reduce(|a, b| {
-        if a.1.translation.xy().distance_squared(player_position)
-            < b.1.translation.xy().distance_squared(player_position)
+    let positions = players
+        .iter()
+        .map(|(transform, _)| transform.translation.xy())
+        .collect::<Vec<_>>();
+
+    for (player_transform, mut nearest_asteroid) in &mut players {
+        let player_position = player_transform.translation.xy();
+        let Some((entity, nearest)) = asteroids.iter().reduce(|a, b| {
+            if a.1.translation.xy().distance_squared(player_position)
+                < b.1.translation.xy().distance_squared(player_position)
+            {
+                a
+            } else {
+                b
+            }
+        }) else {
+            nearest_asteroid.0 = None;
+            continue;
+        };
+        let nearest_position = nearest.translation.xy();
+        nearest_asteroid.0 = Some(nearest_position);
+        let distance = nearest_position - player_position;
+
+        let direction = distance.normalize();
+        if positions
+            .iter()
+            .all(|other| other.distance(nearest_position) > NEAREST_PULL_DISTANCE)
         {
-            a
-        } else {
-            b
+            commands
+                .entity(entity)
+                .insert(LinearVelocity(direction * -NEAREST_PULL_SPEED));
         }
-    }) else {
-        return;
-    };
-    let nearest_position = nearest.translation.xy();
-    let distance = nearest_position - player_position;
-
-    let direction = distance.normalize();
-    if distance.length() > 1000.0 {
-        commands
-            .entity(entity)
-            .insert(LinearVelocity(direction.normalize() * -100.0));
-    }
-    gizmos.arrow_2d(
-        player_position + direction * 45.0,
-        player_position + direction * 70.0,
-        // nearest_position,
-        Color::hsl(0.0, 1.0, 0.5),
-    );
+        gizmos.arrow_2d(
+            player_position + direction * 45.0,
+            player_position + direction * 70.0,
+            // nearest_position,
+            theme.pointer,
+        );
+    }
 }
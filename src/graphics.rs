@@ -0,0 +1,188 @@
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use bevy::{
+    core_pipeline::bloom::{Bloom, BloomPrefilter},
+    prelude::*,
+};
+use bevy_enoki::Particle2dEffect;
+
+use crate::settings::Settings;
+
+pub fn graphics_plugin(app: &mut App) {
+    app.init_resource::<BaseParticleEffects>()
+        .add_observer(setup_camera)
+        .add_systems(
+            Update,
+            (
+                apply_camera.run_if(resource_changed::<Settings>),
+                scale_particles,
+            ),
+        );
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BloomLevel {
+    Off,
+    Low,
+    High,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StarfieldQuality {
+    // Rendered on the CPU into a small texture
+    Low,
+    High,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct GraphicsSettings {
+    pub bloom: BloomLevel,
+    pub bloom_intensity: f32,
+    pub bloom_threshold: f32,
+    pub hdr: bool,
+    // Fraction of the particles from the effect files that are spawned
+    pub particle_density: f32,
+    pub starfield: StarfieldQuality,
+}
+
+impl Default for GraphicsSettings {
+    fn default() -> Self {
+        GraphicsSettings {
+            bloom: BloomLevel::High,
+            bloom_intensity: Bloom::NATURAL.intensity,
+            bloom_threshold: 0.0,
+            hdr: true,
+            particle_density: 1.0,
+            starfield: StarfieldQuality::High,
+        }
+    }
+}
+
+impl GraphicsSettings {
+    // Bloom needs HDR, colours above 1.0 are clamped to white without it
+    fn bloom(&self) -> Option<Bloom> {
+        if !self.hdr {
+            return None;
+        }
+        let max_mip_dimension = match self.bloom {
+            BloomLevel::Off => return None,
+            BloomLevel::Low => Bloom::NATURAL.max_mip_dimension / 2,
+            BloomLevel::High => Bloom::NATURAL.max_mip_dimension,
+        };
+        Some(Bloom {
+            intensity: self.bloom_intensity,
+            prefilter: BloomPrefilter {
+                threshold: self.bloom_threshold,
+                threshold_softness: 0.0,
+            },
+            max_mip_dimension,
+            ..Bloom::NATURAL
+        })
+    }
+}
+
+impl fmt::Display for BloomLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            BloomLevel::Off => "off",
+            BloomLevel::Low => "low",
+            BloomLevel::High => "high",
+        })
+    }
+}
+
+impl FromStr for BloomLevel {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(BloomLevel::Off),
+            "low" => Ok(BloomLevel::Low),
+            "high" => Ok(BloomLevel::High),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for StarfieldQuality {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            StarfieldQuality::Low => "low",
+            StarfieldQuality::High => "high",
+        })
+    }
+}
+
+impl FromStr for StarfieldQuality {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "low" => Ok(StarfieldQuality::Low),
+            "high" => Ok(StarfieldQuality::High),
+            _ => Err(()),
+        }
+    }
+}
+
+fn setup_camera(
+    trigger: Trigger<OnAdd, Camera2d>,
+    mut commands: Commands,
+    mut cameras: Query<&mut Camera>,
+    settings: Res<Settings>,
+) -> Result {
+    cameras.get_mut(trigger.target())?.hdr = settings.graphics.hdr;
+    if let Some(bloom) = settings.graphics.bloom() {
+        commands.entity(trigger.target()).insert(bloom);
+    }
+    Ok(())
+}
+
+fn apply_camera(
+    mut commands: Commands,
+    mut cameras: Query<(Entity, &mut Camera), With<Camera2d>>,
+    settings: Res<Settings>,
+) {
+    for (entity, mut camera) in &mut cameras {
+        camera.hdr = settings.graphics.hdr;
+        match settings.graphics.bloom() {
+            Some(bloom) => commands.entity(entity).insert(bloom),
+            None => commands.entity(entity).remove::<Bloom>(),
+        };
+    }
+}
+
+// Spawn amount and rate of each effect as loaded from its file
+#[derive(Resource, Default)]
+struct BaseParticleEffects(HashMap<AssetId<Particle2dEffect>, (u32, f32)>);
+
+fn scale_particles(
+    mut events: EventReader<AssetEvent<Particle2dEffect>>,
+    mut effects: ResMut<Assets<Particle2dEffect>>,
+    mut base: ResMut<BaseParticleEffects>,
+    settings: Res<Settings>,
+) {
+    let mut scale = Vec::new();
+    for event in events.read() {
+        // Scaling an effect modifies it, only look at newly loaded ones
+        if let AssetEvent::LoadedWithDependencies { id } = event
+            && let Some(effect) = effects.get(*id)
+        {
+            base.0.insert(*id, (effect.spawn_amount, effect.spawn_rate));
+            scale.push(*id);
+        }
+    }
+    if settings.is_changed() {
+        scale = base.0.keys().copied().collect();
+    }
+
+    for id in scale {
+        let (Some(effect), Some(&(amount, rate))) = (effects.get_mut(id), base.0.get(&id)) else {
+            continue;
+        };
+        // Fewer particles per burst, then fewer bursts when down to one per burst
+        let target = amount as f32 * settings.graphics.particle_density.clamp(0.1, 1.0);
+        effect.spawn_amount = (target.round() as u32).max(1);
+        effect.spawn_rate = rate * effect.spawn_amount as f32 / target;
+    }
+}
@@ -0,0 +1,61 @@
+use bevy::prelude::*;
+
+use crate::{GameMode, GameState, game::Pilots, settings::Settings, waves::Wave};
+
+pub fn high_scores_plugin(app: &mut App) {
+    app.init_resource::<HighScores>()
+        .init_resource::<AssistsUsed>()
+        .add_systems(OnEnter(GameState::Game), reset_assists_used)
+        .add_systems(
+            Update,
+            track_assists.run_if(in_state(GameState::Game).and(resource_changed::<Settings>)),
+        )
+        .add_systems(OnExit(GameState::Game), record_high_score);
+}
+
+const MAX_HIGH_SCORES: usize = 5;
+
+#[derive(Resource, Default)]
+pub struct HighScores(pub Vec<HighScore>);
+
+pub struct HighScore {
+    pub score: u32,
+    pub mode: GameMode,
+    pub wave: u32,
+    pub assisted: bool,
+}
+
+// Whether any assist was turned on at some point during the current run
+#[derive(Resource, Default)]
+struct AssistsUsed(bool);
+
+fn reset_assists_used(mut used: ResMut<AssistsUsed>, settings: Res<Settings>) {
+    used.0 = settings.assists.any();
+}
+
+fn track_assists(mut used: ResMut<AssistsUsed>, settings: Res<Settings>) {
+    used.0 |= settings.assists.any();
+}
+
+fn record_high_score(
+    pilots: Res<Pilots>,
+    game_mode: Res<GameMode>,
+    wave: Res<Wave>,
+    assists_used: Res<AssistsUsed>,
+    mut high_scores: ResMut<HighScores>,
+) {
+    if *game_mode == GameMode::Versus {
+        return;
+    }
+
+    high_scores.0.push(HighScore {
+        score: pilots.total_score(),
+        mode: *game_mode,
+        wave: wave.0,
+        assisted: assists_used.0,
+    });
+    high_scores
+        .0
+        .sort_by_key(|high_score| std::cmp::Reverse(high_score.score));
+    high_scores.0.truncate(MAX_HIGH_SCORES);
+}
@@ -1,51 +1,128 @@
-use bevy::{prelude::*, time::Stopwatch};
+use bevy::prelude::*;
 
 use crate::{
-    GameState,
-    game::{Asteroid, LivesRemaining},
+    GameMode, GameState,
+    game::{Asteroid, Pilots, Player, Shield},
+    localization::{Locale, Localized},
+    objectives::{GameDuration, ObjectiveProgress},
+    theme::Themed,
+    waves::Wave,
 };
 
 pub fn hud_plugin(app: &mut App) {
     app.add_systems(OnEnter(GameState::Game), display_hud)
-        .add_systems(Update, update_hud.run_if(in_state(GameState::Game)));
+        .add_systems(
+            Update,
+            (update_hud, update_player_hud, update_objectives_hud)
+                .run_if(in_state(GameState::Game)),
+        );
 }
 
-#[derive(Resource)]
-struct GameDuration(Stopwatch);
+#[derive(Component)]
+struct Hud;
 
 fn display_hud(mut commands: Commands) {
-    commands.spawn((
-        Text::default(),
-        StateScoped(GameState::Game),
-        children![
-            TextSpan::new("Asteroids remaining: "),
-            TextSpan::new("0"),
-            TextSpan::new("\n"),
-            TextSpan::new("Lives remaining: "),
-            TextSpan::new("0"),
-            TextSpan::new("\n"),
-            TextSpan::new("Time: "),
-            TextSpan::new("0")
-        ],
-    ));
-
-    commands.insert_resource(GameDuration(Stopwatch::new()));
+    commands
+        .spawn((
+            Text::default(),
+            Themed::Hud,
+            Hud,
+            StateScoped(GameState::Game),
+        ))
+        .with_children(|parent| {
+            for (i, (label, value)) in [
+                ("hud-asteroids", "0"),
+                ("hud-lives", "0"),
+                ("hud-time", "0"),
+                ("hud-shield", "-"),
+                ("hud-score", "0"),
+                ("hud-wave", "-"),
+                ("hud-objectives", "-"),
+            ]
+            .into_iter()
+            .enumerate()
+            {
+                if i > 0 {
+                    parent.spawn(TextSpan::new("\n"));
+                }
+                parent.spawn((TextSpan::default(), Localized::new(label), Themed::Hud));
+                parent.spawn((TextSpan::new(value), Themed::Hud));
+            }
+        });
 }
 
 fn update_hud(
-    time: Res<Time>,
-    mut duration: ResMut<GameDuration>,
-    text: Query<Entity, With<Text>>,
+    duration: Res<GameDuration>,
+    text: Query<Entity, With<Hud>>,
     mut writer: TextUiWriter,
     asteroids: Query<(), With<Asteroid>>,
-    lives_remaining: Res<LivesRemaining>,
+    pilots: Res<Pilots>,
+    locale: Locale,
 ) -> Result {
-    duration.0.tick(time.delta());
-
     let text = text.single()?;
     *writer.text(text, 2) = format!("{}", asteroids.iter().len());
-    *writer.text(text, 5) = format!("{}", lives_remaining.0);
-    *writer.text(text, 8) = format!("{:?}s", duration.0.elapsed().as_secs());
+    *writer.text(text, 5) = per_player(pilots.0.iter().map(|pilot| pilot.lives));
+    *writer.text(text, 8) = locale.format(
+        "hud-seconds",
+        &[("seconds", duration.0.elapsed().as_secs().to_string())],
+    );
+
+    Ok(())
+}
+
+fn update_player_hud(
+    text: Query<Entity, With<Hud>>,
+    mut writer: TextUiWriter,
+    shields: Query<(&Player, &Shield)>,
+    pilots: Res<Pilots>,
+    game_mode: Res<GameMode>,
+    wave: Res<Wave>,
+) -> Result {
+    let text = text.single()?;
+    if !shields.is_empty() {
+        let mut levels = vec!["-".to_string(); pilots.0.len()];
+        for (player, shield) in &shields {
+            levels[player.0] = format!("{:.0}%", shield.fraction() * 100.0);
+        }
+        *writer.text(text, 11) = per_player(levels);
+    }
+    *writer.text(text, 14) = per_player(pilots.0.iter().map(|pilot| pilot.score));
+    if *game_mode == GameMode::Endless {
+        *writer.text(text, 17) = format!("{}", wave.0);
+    }
+
+    Ok(())
+}
+
+fn per_player<T: ToString>(values: impl IntoIterator<Item = T>) -> String {
+    values
+        .into_iter()
+        .map(|value| value.to_string())
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
+
+fn update_objectives_hud(
+    text: Query<Entity, With<Hud>>,
+    mut writer: TextUiWriter,
+    objectives: ObjectiveProgress,
+    game_mode: Res<GameMode>,
+    locale: Locale,
+) -> Result {
+    if !game_mode.has_objectives() {
+        return Ok(());
+    }
+
+    let text = text.single()?;
+    let progress = objectives.progress();
+    *writer.text(text, 20) = objectives
+        .level
+        .get()
+        .objectives
+        .iter()
+        .map(|objective| objective.describe(&progress, &locale))
+        .collect::<Vec<_>>()
+        .join(" | ");
 
     Ok(())
 }
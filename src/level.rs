@@ -1,15 +1,162 @@
+use std::str::FromStr;
+
 use bevy::{
     asset::{AssetLoader, AsyncReadExt, LoadContext, io::Reader},
+    ecs::system::SystemParam,
     prelude::*,
 };
 use thiserror::Error;
 
+use crate::{
+    LoadedLevel,
+    objectives::{ClearAsteroids, LimitedAmmo, Objective, Survive, TargetScore, TimeLimit},
+};
+
 #[derive(Asset, TypePath)]
 pub struct Level {
     pub width: u32,
     pub height: u32,
     pub asteroids: u32,
     pub lives: u32,
+    pub shield: Option<ShieldSettings>,
+    pub hyperspace: HyperspaceSettings,
+    pub camera: CameraSettings,
+    pub zoom: ZoomSettings,
+    pub starfield: StarfieldSettings,
+    pub backgrounds: Vec<BackgroundLayer>,
+    pub objectives: Vec<Box<dyn Objective>>,
+    pub ammo: Option<u32>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ShieldSettings {
+    pub capacity: f32,
+    pub regeneration: f32,
+}
+
+#[derive(SystemParam)]
+pub struct CurrentLevel<'w> {
+    loaded_level: Res<'w, LoadedLevel>,
+    levels: Res<'w, Assets<Level>>,
+}
+
+impl CurrentLevel<'_> {
+    pub fn get(&self) -> &Level {
+        self.levels.get(&self.loaded_level.level).unwrap()
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct HyperspaceSettings {
+    pub cooldown: f32,
+    pub risk: f32,
+}
+
+impl Default for HyperspaceSettings {
+    fn default() -> Self {
+        HyperspaceSettings {
+            cooldown: 5.0,
+            risk: 0.1,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct CameraSettings {
+    pub smoothing: f32,
+    pub look_ahead: f32,
+    pub deadzone: f32,
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        CameraSettings {
+            smoothing: 6.0,
+            look_ahead: 0.5,
+            deadzone: 40.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ZoomSettings {
+    pub min: f32,
+    pub max: f32,
+}
+
+impl Default for ZoomSettings {
+    fn default() -> Self {
+        ZoomSettings { min: 1.0, max: 2.0 }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct StarLayer {
+    // Number of star cells across the screen
+    pub density: f32,
+    pub size: f32,
+    pub brightness: f32,
+    // Screen widths scrolled per 1000 units of camera movement
+    pub parallax: f32,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct StarfieldSettings {
+    pub layers: [StarLayer; 3],
+    pub twinkle: f32,
+}
+
+impl Default for StarfieldSettings {
+    fn default() -> Self {
+        StarfieldSettings {
+            layers: [
+                StarLayer {
+                    density: 3.0,
+                    size: 0.025,
+                    brightness: 43.2,
+                    parallax: 1.0 / 1.2,
+                },
+                StarLayer {
+                    density: 10.0,
+                    size: 0.018,
+                    brightness: 40.2,
+                    parallax: 1.0 / 1.4,
+                },
+                StarLayer {
+                    density: 30.0,
+                    size: 0.015,
+                    brightness: 2.25,
+                    parallax: 1.0 / 2.0,
+                },
+            ],
+            twinkle: 0.6,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum BackgroundKind {
+    Tiled {
+        image: String,
+        scale: f32,
+    },
+    Planet {
+        image: String,
+        position: Vec2,
+        scale: f32,
+    },
+    Clouds {
+        seed: f32,
+        scale: f32,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub struct BackgroundLayer {
+    pub kind: BackgroundKind,
+    // 0 stays fixed on screen, 1 moves with the gameplay
+    pub parallax: f32,
+    pub tint: Color,
 }
 
 pub fn level_loader_plugin(app: &mut App) {
@@ -20,13 +167,18 @@ pub fn level_loader_plugin(app: &mut App) {
 struct LevelLoader;
 
 #[derive(Debug, Error)]
-enum LevelLoaderError {
+pub enum LevelLoaderError {
     #[error("Could not load asset: {0}")]
     Io(#[from] std::io::Error),
     #[error("Error in file format")]
     FormatError,
 }
 
+fn parse<T: FromStr>(word: Option<&str>) -> Result<T, LevelLoaderError> {
+    word.and_then(|s| s.parse().ok())
+        .ok_or(LevelLoaderError::FormatError)
+}
+
 impl AssetLoader for LevelLoader {
     type Asset = Level;
     type Settings = ();
@@ -40,28 +192,147 @@ impl AssetLoader for LevelLoader {
         let mut buf = String::new();
         reader.read_to_string(&mut buf).await?;
 
-        let mut lines = buf.lines();
-        Ok(Level {
-            width: lines
-                .next()
-                .and_then(|s| s.parse().ok())
-                .ok_or(LevelLoaderError::FormatError)?,
-            height: lines
-                .next()
-                .and_then(|s| s.parse().ok())
-                .ok_or(LevelLoaderError::FormatError)?,
-            asteroids: lines
-                .next()
-                .and_then(|s| s.parse().ok())
-                .ok_or(LevelLoaderError::FormatError)?,
-            lives: lines
-                .next()
-                .and_then(|s| s.parse().ok())
-                .ok_or(LevelLoaderError::FormatError)?,
-        })
+        buf.parse()
     }
 
     fn extensions(&self) -> &[&str] {
         &["bw"]
     }
 }
+
+impl FromStr for Level {
+    type Err = LevelLoaderError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.lines();
+        let mut level = Level {
+            width: parse(lines.next())?,
+            height: parse(lines.next())?,
+            asteroids: parse(lines.next())?,
+            lives: parse(lines.next())?,
+            shield: None,
+            hyperspace: HyperspaceSettings::default(),
+            camera: CameraSettings::default(),
+            zoom: ZoomSettings::default(),
+            starfield: StarfieldSettings::default(),
+            backgrounds: vec![],
+            objectives: vec![],
+            ammo: None,
+        };
+
+        // Optional settings, one per line: a key followed by its values
+        for line in lines.filter(|line| !line.trim().is_empty()) {
+            let mut words = line.split_whitespace();
+            match words.next() {
+                Some("shield") => {
+                    let shield = ShieldSettings {
+                        capacity: parse(words.next())?,
+                        regeneration: parse(words.next())?,
+                    };
+                    // Leave the line out for no shield
+                    if shield.capacity <= 0.0 {
+                        return Err(LevelLoaderError::FormatError);
+                    }
+                    level.shield = Some(shield);
+                }
+                Some("hyperspace") => {
+                    level.hyperspace = HyperspaceSettings {
+                        cooldown: parse(words.next())?,
+                        risk: parse(words.next())?,
+                    };
+                }
+                Some("camera") => {
+                    level.camera = CameraSettings {
+                        smoothing: parse(words.next())?,
+                        look_ahead: parse(words.next())?,
+                        deadzone: parse(words.next())?,
+                    };
+                }
+                Some("zoom") => {
+                    let zoom = ZoomSettings {
+                        min: parse(words.next())?,
+                        max: parse(words.next())?,
+                    };
+                    // The camera clamps between these, which needs min <= max
+                    let valid = |value: f32| value.is_finite() && value > 0.0;
+                    if !valid(zoom.min) || !valid(zoom.max) || zoom.min > zoom.max {
+                        return Err(LevelLoaderError::FormatError);
+                    }
+                    level.zoom = zoom;
+                }
+                Some("stars") => {
+                    let layer = level
+                        .starfield
+                        .layers
+                        .get_mut(parse::<usize>(words.next())?)
+                        .ok_or(LevelLoaderError::FormatError)?;
+                    *layer = StarLayer {
+                        density: parse(words.next())?,
+                        size: parse(words.next())?,
+                        brightness: parse(words.next())?,
+                        parallax: parse(words.next())?,
+                    };
+                }
+                Some("twinkle") => {
+                    level.starfield.twinkle = parse(words.next())?;
+                }
+                Some("background") => {
+                    let kind = match words.next() {
+                        Some("tiled") => BackgroundKind::Tiled {
+                            image: parse(words.next())?,
+                            scale: parse(words.next())?,
+                        },
+                        Some("planet") => BackgroundKind::Planet {
+                            image: parse(words.next())?,
+                            position: Vec2::new(parse(words.next())?, parse(words.next())?),
+                            scale: parse(words.next())?,
+                        },
+                        Some("clouds") => BackgroundKind::Clouds {
+                            seed: parse(words.next())?,
+                            scale: parse(words.next())?,
+                        },
+                        _ => return Err(LevelLoaderError::FormatError),
+                    };
+                    // Tiles and clouds divide by their scale
+                    if let BackgroundKind::Tiled { scale, .. }
+                    | BackgroundKind::Clouds { scale, .. } = kind
+                        && !(scale.is_finite() && scale > 0.0)
+                    {
+                        return Err(LevelLoaderError::FormatError);
+                    }
+                    level.backgrounds.push(BackgroundLayer {
+                        kind,
+                        parallax: parse(words.next())?,
+                        tint: Color::srgba(
+                            parse(words.next())?,
+                            parse(words.next())?,
+                            parse(words.next())?,
+                            parse(words.next())?,
+                        ),
+                    });
+                }
+                Some("objective") => {
+                    let objective: Box<dyn Objective> = match words.next() {
+                        Some("clear") => Box::new(ClearAsteroids),
+                        Some("time") => Box::new(TimeLimit(parse(words.next())?)),
+                        Some("score") => Box::new(TargetScore(parse(words.next())?)),
+                        Some("survive") => Box::new(Survive(parse(words.next())?)),
+                        _ => return Err(LevelLoaderError::FormatError),
+                    };
+                    level.objectives.push(objective);
+                }
+                Some("ammo") => {
+                    level.ammo = Some(parse(words.next())?);
+                    level.objectives.push(Box::new(LimitedAmmo));
+                }
+                _ => return Err(LevelLoaderError::FormatError),
+            }
+        }
+
+        if !level.objectives.iter().any(|objective| objective.is_goal()) {
+            level.objectives.push(Box::new(ClearAsteroids));
+        }
+
+        Ok(level)
+    }
+}
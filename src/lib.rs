@@ -0,0 +1,146 @@
+use avian2d::{PhysicsPlugins, prelude::Gravity};
+use bevy::prelude::*;
+use bevy_enhanced_input::EnhancedInputPlugin;
+use bevy_enoki::{EnokiPlugin, Particle2dEffect};
+use level::Level;
+
+mod assists;
+mod audio;
+mod background;
+mod camera;
+mod effects;
+mod game;
+mod graphics;
+mod high_scores;
+mod hud;
+mod level;
+mod localization;
+mod music;
+#[cfg(not(target_arch = "wasm32"))]
+mod net;
+mod objectives;
+mod pause;
+mod settings;
+mod settings_menu;
+mod splash;
+mod starfield;
+mod start_menu;
+mod theme;
+mod waves;
+mod won;
+
+// Runs the headless server, see `src/bin/server.rs`
+#[cfg(not(target_arch = "wasm32"))]
+pub fn run_server() {
+    net::run_server_from_args();
+}
+
+// Runs the game, or joins a networked match when started with network flags
+pub fn run() {
+    #[cfg(not(target_arch = "wasm32"))]
+    match net::NetMode::from_args() {
+        Some(net::NetMode::Client { address }) => return net::run_client(address),
+        Some(net::NetMode::Rollback(config)) => return net::run_rollback(config),
+        None => (),
+    }
+
+    App::new()
+        .add_plugins(DefaultPlugins.set(WindowPlugin {
+            primary_window: Some(Window {
+                title: "Bevy Workshop".into(),
+                canvas: Some("#game".into()),
+                ..default()
+            }),
+            ..default()
+        }))
+        .init_state::<GameState>()
+        .init_resource::<GameMode>()
+        .enable_state_scoped_entities::<GameState>()
+        .add_plugins((PhysicsPlugins::default(), EnhancedInputPlugin, EnokiPlugin))
+        .insert_resource(Gravity::ZERO)
+        .add_plugins((
+            splash::splash_plugin,
+            start_menu::menu_plugin,
+            game::game_plugin,
+            level::level_loader_plugin,
+            hud::hud_plugin,
+            won::won_plugin,
+            (audio::audio_plugin, music::music_plugin),
+            (
+                background::background_plugin,
+                graphics::graphics_plugin,
+                theme::theme_plugin,
+            ),
+            camera::camera_plugin,
+            effects::effects_plugin,
+            starfield::starfield_plugin,
+            waves::waves_plugin,
+            high_scores::high_scores_plugin,
+            objectives::objectives_plugin,
+            (
+                settings::settings_plugin,
+                settings_menu::settings_menu_plugin,
+                pause::pause_plugin,
+                assists::assists_plugin,
+                localization::localization_plugin,
+            ),
+        ))
+        .run();
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, States, Default)]
+enum GameState {
+    #[default]
+    Splash,
+    StartMenu,
+    Game,
+    Won,
+}
+
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum GameMode {
+    #[default]
+    Campaign,
+    Endless,
+    Coop,
+    Versus,
+}
+
+impl GameMode {
+    fn player_count(&self) -> usize {
+        match self {
+            GameMode::Campaign | GameMode::Endless => 1,
+            GameMode::Coop | GameMode::Versus => 2,
+        }
+    }
+
+    fn has_objectives(&self) -> bool {
+        matches!(self, GameMode::Campaign | GameMode::Coop)
+    }
+}
+
+#[derive(Resource)]
+struct GameAssets {
+    player_ship: Handle<Image>,
+    asteroid: Handle<Image>,
+    asteroid_cracks: [Handle<Image>; 2],
+    jets: Handle<Image>,
+    explosion: Handle<Image>,
+    laser: Handle<Image>,
+    jet_particles: Handle<Particle2dEffect>,
+    ship_explosion_particles: Handle<Particle2dEffect>,
+    asteroid_debris_particles: Handle<Particle2dEffect>,
+    laser_hit_particles: Handle<Particle2dEffect>,
+}
+
+#[derive(Resource)]
+pub struct LoadedLevel {
+    pub level: Handle<Level>,
+}
+
+#[derive(Resource)]
+struct AudioAssets {
+    sound_bank: Handle<audio::SoundBank>,
+    // Music stems, from calm to intense
+    music: Vec<Handle<AudioSource>>,
+}
@@ -0,0 +1,333 @@
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use bevy::{
+    asset::{AssetLoader, AsyncReadExt, LoadContext, io::Reader},
+    ecs::system::SystemParam,
+    prelude::*,
+    window::PrimaryWindow,
+};
+use thiserror::Error;
+
+use crate::settings::Settings;
+
+pub fn localization_plugin(app: &mut App) {
+    app.init_asset::<StringTable>()
+        .init_asset_loader::<StringTableLoader>()
+        .init_resource::<Locales>()
+        .add_observer(localize_new_text)
+        .add_observer(set_new_text_font)
+        .add_systems(PreUpdate, relocalize);
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum Language {
+    #[default]
+    English,
+    Russian,
+}
+
+impl Language {
+    const ALL: [Language; 2] = [Language::English, Language::Russian];
+
+    fn index(self) -> usize {
+        Self::ALL
+            .iter()
+            .position(|language| *language == self)
+            .unwrap()
+    }
+
+    pub fn next(self) -> Self {
+        Self::ALL[(self.index() + 1) % Self::ALL.len()]
+    }
+
+    pub fn previous(self) -> Self {
+        Self::ALL[(self.index() + Self::ALL.len() - 1) % Self::ALL.len()]
+    }
+
+    // Bevy's default font only covers Latin, other scripts need their own font
+    fn font(&self) -> Option<&'static str> {
+        match self {
+            Language::English => None,
+            Language::Russian => Some("fonts/NotoSans-Regular.ttf"),
+        }
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Language::English => "en",
+            Language::Russian => "ru",
+        })
+    }
+}
+
+impl FromStr for Language {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "en" => Ok(Language::English),
+            "ru" => Ok(Language::Russian),
+            _ => Err(()),
+        }
+    }
+}
+
+enum Segment {
+    Text(String),
+    Variable(String),
+}
+
+// Messages of one language, from a subset of the Fluent syntax: `key = value`
+// lines with `{ $variable }` and `{ "literal" }` placeables, and `#` comments
+#[derive(Asset, TypePath)]
+pub struct StringTable(HashMap<String, Vec<Segment>>);
+
+#[derive(Default)]
+struct StringTableLoader;
+
+#[derive(Debug, Error)]
+pub enum StringTableLoaderError {
+    #[error("Could not load asset: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Error in file format on line {0}")]
+    FormatError(usize),
+}
+
+impl AssetLoader for StringTableLoader {
+    type Asset = StringTable;
+    type Settings = ();
+    type Error = StringTableLoaderError;
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut buf = String::new();
+        reader.read_to_string(&mut buf).await?;
+
+        buf.parse()
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ftl"]
+    }
+}
+
+impl FromStr for StringTable {
+    type Err = StringTableLoaderError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut messages = HashMap::new();
+        for (index, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = || StringTableLoaderError::FormatError(index + 1);
+            let (key, value) = line.split_once('=').ok_or_else(error)?;
+            let key = key.trim();
+            if key.is_empty() || !key.chars().all(|c| c.is_alphanumeric() || "-_".contains(c)) {
+                return Err(error());
+            }
+            let pattern = parse_pattern(value.trim()).ok_or_else(error)?;
+            messages.insert(key.to_string(), pattern);
+        }
+        Ok(StringTable(messages))
+    }
+}
+
+fn parse_pattern(mut value: &str) -> Option<Vec<Segment>> {
+    let mut segments = vec![];
+    while let Some(start) = value.find('{') {
+        if start > 0 {
+            segments.push(Segment::Text(value[..start].to_string()));
+        }
+        let end = start + value[start..].find('}')?;
+        let placeable = value[start + 1..end].trim();
+        if let Some(variable) = placeable.strip_prefix('$') {
+            segments.push(Segment::Variable(variable.to_string()));
+        } else {
+            let literal = placeable.strip_prefix('"')?.strip_suffix('"')?;
+            segments.push(Segment::Text(literal.to_string()));
+        }
+        value = &value[end + 1..];
+    }
+    if !value.is_empty() {
+        segments.push(Segment::Text(value.to_string()));
+    }
+    Some(segments)
+}
+
+struct LocaleAssets {
+    strings: Handle<StringTable>,
+    font: Handle<Font>,
+}
+
+#[derive(Resource)]
+struct Locales(HashMap<Language, LocaleAssets>);
+
+impl FromWorld for Locales {
+    fn from_world(world: &mut World) -> Self {
+        let asset_server = world.resource::<AssetServer>();
+        Locales(
+            Language::ALL
+                .into_iter()
+                .map(|language| {
+                    let assets = LocaleAssets {
+                        strings: asset_server.load(format!("locales/{language}.ftl")),
+                        font: language
+                            .font()
+                            .map(|path| asset_server.load(path))
+                            .unwrap_or_default(),
+                    };
+                    (language, assets)
+                })
+                .collect(),
+        )
+    }
+}
+
+impl Locales {
+    // Text in the default font or a language font follows the language, text
+    // with any other font keeps it
+    fn follows_language(&self, font: &Handle<Font>) -> bool {
+        self.0.values().any(|assets| assets.font == *font)
+    }
+}
+
+// Looks up messages in the language from the settings
+#[derive(SystemParam)]
+pub struct Locale<'w> {
+    settings: Res<'w, Settings>,
+    locales: Res<'w, Locales>,
+    tables: Res<'w, Assets<StringTable>>,
+}
+
+impl Locale<'_> {
+    fn table(&self, language: Language) -> Option<&StringTable> {
+        self.tables.get(&self.locales.0[&language].strings)
+    }
+
+    fn is_loaded(&self) -> bool {
+        self.table(self.settings.language).is_some() && self.table(Language::English).is_some()
+    }
+
+    fn font(&self) -> Handle<Font> {
+        self.locales.0[&self.settings.language].font.clone()
+    }
+
+    pub fn get(&self, key: &str) -> String {
+        self.format(key, &[])
+    }
+
+    // Messages missing from a translation fall back to English, then to their key
+    pub fn format(&self, key: &str, args: &[(&str, String)]) -> String {
+        let Some(pattern) = [self.settings.language, Language::English]
+            .into_iter()
+            .filter_map(|language| self.table(language)?.0.get(key))
+            .next()
+        else {
+            return key.to_string();
+        };
+
+        let mut message = String::new();
+        for segment in pattern {
+            match segment {
+                Segment::Text(text) => message.push_str(text),
+                Segment::Variable(name) => match args.iter().find(|(arg, _)| arg == name) {
+                    Some((_, value)) => message.push_str(value),
+                    None => message.push_str(&format!("{{${name}}}")),
+                },
+            }
+        }
+        message
+    }
+}
+
+// Text or span showing a message, updated when the language changes
+#[derive(Component, Clone, Debug)]
+pub struct Localized {
+    key: &'static str,
+    args: Vec<(&'static str, String)>,
+}
+
+impl Localized {
+    pub fn new(key: &'static str) -> Self {
+        Localized { key, args: vec![] }
+    }
+
+    pub fn with_arg(mut self, name: &'static str, value: impl ToString) -> Self {
+        self.args.push((name, value.to_string()));
+        self
+    }
+}
+
+fn localize(
+    localized: &Localized,
+    text: Option<Mut<Text>>,
+    span: Option<Mut<TextSpan>>,
+    locale: &Locale,
+) {
+    let message = locale.format(localized.key, &localized.args);
+    if let Some(mut text) = text {
+        text.0 = message;
+    } else if let Some(mut span) = span {
+        span.0 = message;
+    }
+}
+
+fn localize_new_text(
+    trigger: Trigger<OnInsert, Localized>,
+    mut texts: Query<(&Localized, Option<&mut Text>, Option<&mut TextSpan>)>,
+    locale: Locale,
+) -> Result {
+    // Texts spawned before the tables are loaded are filled in by relocalize
+    if locale.is_loaded() {
+        let (localized, text, span) = texts.get_mut(trigger.target())?;
+        localize(localized, text, span, &locale);
+    }
+    Ok(())
+}
+
+fn set_new_text_font(
+    trigger: Trigger<OnAdd, TextFont>,
+    mut fonts: Query<&mut TextFont>,
+    locale: Locale,
+) -> Result {
+    let mut text_font = fonts.get_mut(trigger.target())?;
+    if locale.locales.follows_language(&text_font.font) {
+        text_font.font = locale.font();
+    }
+    Ok(())
+}
+
+fn relocalize(
+    mut events: EventReader<AssetEvent<StringTable>>,
+    mut current: Local<Option<Language>>,
+    mut texts: Query<(&Localized, Option<&mut Text>, Option<&mut TextSpan>)>,
+    mut fonts: Query<&mut TextFont>,
+    mut window: Query<&mut Window, With<PrimaryWindow>>,
+    locale: Locale,
+) {
+    let tables_changed = events.read().count() > 0;
+    if !locale.is_loaded() || !tables_changed && *current == Some(locale.settings.language) {
+        return;
+    }
+    *current = Some(locale.settings.language);
+
+    for (localized, text, span) in &mut texts {
+        localize(localized, text, span, &locale);
+    }
+    let font = locale.font();
+    for mut text_font in &mut fonts {
+        if text_font.font != font && locale.locales.follows_language(&text_font.font) {
+            text_font.font = font.clone();
+        }
+    }
+    if let Ok(mut window) = window.single_mut() {
+        window.title = locale.get("window-title");
+    }
+}
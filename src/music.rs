@@ -0,0 +1,155 @@
+use std::f32::consts::FRAC_PI_2;
+
+use bevy::{audio::Volume, prelude::*};
+
+use crate::{
+    AudioAssets, GameState,
+    audio::AudioBus,
+    game::{Asteroid, Pilots, Player},
+    objectives::ObjectiveProgress,
+    settings::Settings,
+};
+
+const MUSIC_VOLUME: Volume = Volume::Decibels(-5.0);
+const FADE_IN: f32 = 1.0;
+const FADE_OUT: f32 = 2.0;
+// How fast the intensity can change, per second
+const INTENSITY_RATE: f32 = 0.4;
+// Asteroids within this distance of a ship are a threat
+const THREAT_RADIUS: f32 = 600.0;
+const MAX_THREATS: f32 = 6.0;
+// Seconds left on a time limit when the pressure starts to build
+const TIME_PRESSURE: f32 = 30.0;
+
+pub fn music_plugin(app: &mut App) {
+    app.init_resource::<Intensity>()
+        .add_systems(OnEnter(GameState::Game), start_music)
+        .add_systems(OnExit(GameState::Game), fade_out_music)
+        .add_systems(
+            Update,
+            (
+                update_intensity.run_if(in_state(GameState::Game)),
+                mix_stems,
+            )
+                .chain(),
+        );
+}
+
+#[derive(Resource, Default)]
+struct Intensity(f32);
+
+#[derive(Component)]
+pub struct MusicStem {
+    index: usize,
+    fade: f32,
+    fading_out: bool,
+}
+
+fn start_music(
+    mut commands: Commands,
+    audio_assets: Res<AudioAssets>,
+    mut intensity: ResMut<Intensity>,
+) {
+    intensity.0 = 0.0;
+
+    // All stems play in sync, and are mixed according to the intensity
+    for (index, stem) in audio_assets.music.iter().enumerate() {
+        commands.spawn((
+            AudioPlayer::<AudioSource>(stem.clone()),
+            PlaybackSettings::LOOP.with_volume(Volume::Linear(0.0)),
+            AudioBus::Music,
+            MusicStem {
+                index,
+                fade: 0.0,
+                fading_out: false,
+            },
+        ));
+    }
+}
+
+fn fade_out_music(mut stems: Query<&mut MusicStem>) {
+    for mut stem in &mut stems {
+        stem.fading_out = true;
+    }
+}
+
+fn update_intensity(
+    mut intensity: ResMut<Intensity>,
+    ships: Query<&Transform, With<Player>>,
+    asteroids: Query<&Transform, With<Asteroid>>,
+    pilots: Res<Pilots>,
+    objectives: ObjectiveProgress,
+    time: Res<Time<Real>>,
+) {
+    let threats = asteroids
+        .iter()
+        .filter(|asteroid| {
+            ships.iter().any(|ship| {
+                ship.translation.xy().distance(asteroid.translation.xy()) < THREAT_RADIUS
+            })
+        })
+        .count();
+    let threat = (threats as f32 / MAX_THREATS).min(1.0);
+
+    // Ships left for the pilot closest to being eliminated, including the one in play
+    let level = objectives.level.get();
+    let ships_left = pilots
+        .0
+        .iter()
+        .filter(|pilot| !pilot.eliminated)
+        .map(|pilot| pilot.lives + 1)
+        .min()
+        .unwrap_or(level.lives);
+    let danger = 1.0 - ships_left as f32 / level.lives.max(1) as f32;
+
+    let progress = objectives.progress();
+    let pressure = level
+        .objectives
+        .iter()
+        .filter_map(|objective| objective.time_left(&progress))
+        .map(|time_left| 1.0 - time_left / TIME_PRESSURE)
+        .fold(0.0, f32::max);
+
+    let target = (threat * 0.6 + danger * 0.25 + pressure.clamp(0.0, 1.0) * 0.5).min(1.0);
+    let step = INTENSITY_RATE * time.delta_secs();
+    intensity.0 += (target - intensity.0).clamp(-step, step);
+}
+
+fn mix_stems(
+    mut commands: Commands,
+    mut stems: Query<(Entity, &mut MusicStem, Option<&mut AudioSink>)>,
+    audio_assets: Res<AudioAssets>,
+    intensity: Res<Intensity>,
+    settings: Res<Settings>,
+    time: Res<Time<Real>>,
+) {
+    // Equal power crossfade between the two stems closest to the intensity
+    let position = intensity.0 * audio_assets.music.len().saturating_sub(1) as f32;
+
+    for (entity, mut stem, sink) in &mut stems {
+        if stem.fading_out {
+            stem.fade -= time.delta_secs() / FADE_OUT;
+            if stem.fade <= 0.0 {
+                commands.entity(entity).despawn();
+                continue;
+            }
+        } else {
+            stem.fade = (stem.fade + time.delta_secs() / FADE_IN).min(1.0);
+        }
+
+        let Some(mut sink) = sink else {
+            continue;
+        };
+        let distance = (position - stem.index as f32).abs();
+        let weight = if distance < 1.0 {
+            (distance * FRAC_PI_2).cos()
+        } else {
+            0.0
+        };
+        sink.set_volume(
+            MUSIC_VOLUME
+                * Volume::Linear(weight * stem.fade)
+                * settings.mixer.gain(AudioBus::Music),
+        );
+    }
+}
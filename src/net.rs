@@ -0,0 +1,76 @@
+use std::net::{SocketAddr, UdpSocket};
+
+use crate::GameMode;
+
+mod client;
+mod protocol;
+mod rollback;
+mod server;
+mod simulation;
+
+pub use client::run_client;
+pub use rollback::{SessionConfig, run_rollback};
+
+const DEFAULT_PORT: u16 = 7777;
+
+pub enum NetMode {
+    Client { address: SocketAddr },
+    Rollback(SessionConfig),
+}
+
+fn arg_value(args: &[String], flag: &str) -> Option<String> {
+    args.iter()
+        .position(|arg| arg == flag)
+        .and_then(|index| args.get(index + 1))
+        .cloned()
+}
+
+// Arguments of the server binary: `--port PORT --mode coop|versus|campaign|endless`
+pub fn run_server_from_args() {
+    let args = std::env::args().collect::<Vec<_>>();
+    let port = arg_value(&args, "--port").map_or(DEFAULT_PORT, |port| {
+        port.parse().expect("Invalid server port")
+    });
+    let game_mode = match arg_value(&args, "--mode").as_deref() {
+        None | Some("coop") => GameMode::Coop,
+        Some("versus") => GameMode::Versus,
+        Some("campaign") => GameMode::Campaign,
+        Some("endless") => GameMode::Endless,
+        Some(other) => panic!("Unknown game mode {other}"),
+    };
+    server::run_server(port, game_mode);
+}
+
+impl NetMode {
+    pub fn from_args() -> Option<Self> {
+        let args = std::env::args().collect::<Vec<_>>();
+        let value_of = |flag: &str| arg_value(&args, flag);
+
+        if let Some(port) = value_of("--rollback") {
+            let socket = UdpSocket::bind(("0.0.0.0", port.parse().expect("Invalid port")))
+                .expect("Could not bind rollback socket");
+            socket.set_nonblocking(true).unwrap();
+            let player = value_of("--player").map_or(1, |player| {
+                player.parse::<usize>().expect("Invalid player number")
+            });
+            Some(NetMode::Rollback(SessionConfig::P2p {
+                socket,
+                peer: value_of("--peer")
+                    .expect("Missing --peer address")
+                    .parse()
+                    .expect("Invalid peer address"),
+                local_player: player.clamp(1, 2) - 1,
+            }))
+        } else if args.iter().any(|arg| arg == "--synctest") {
+            Some(NetMode::Rollback(SessionConfig::SyncTest {
+                check_distance: value_of("--synctest")
+                    .and_then(|distance| distance.parse().ok())
+                    .unwrap_or(7),
+            }))
+        } else {
+            value_of("--connect").map(|address| NetMode::Client {
+                address: address.parse().expect("Invalid server address"),
+            })
+        }
+    }
+}
@@ -0,0 +1,397 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    io::ErrorKind,
+    net::{SocketAddr, UdpSocket},
+};
+
+use bevy::prelude::*;
+use bevy_enoki::EnokiPlugin;
+
+use super::protocol::{ClientMessage, NetEntity, NetKind, RemoteInput, ServerMessage, Snapshot};
+use crate::{
+    GameAssets, GameState,
+    audio::AudioStart,
+    localization::{Locale, Localized},
+    settings::Settings,
+    theme::Theme,
+};
+
+const INTERPOLATION_DELAY: f32 = 0.1;
+// Without any message for this long, the server is considered gone
+const SERVER_TIMEOUT: f32 = 5.0;
+
+pub fn run_client(address: SocketAddr) {
+    let socket = UdpSocket::bind(("0.0.0.0", 0)).expect("Could not bind client socket");
+    socket.set_nonblocking(true).unwrap();
+
+    App::new()
+        .add_plugins(DefaultPlugins.set(WindowPlugin {
+            primary_window: Some(Window {
+                title: "Bevy Workshop".into(),
+                ..default()
+            }),
+            ..default()
+        }))
+        .init_state::<GameState>()
+        .enable_state_scoped_entities::<GameState>()
+        .add_plugins(EnokiPlugin)
+        .add_plugins((
+            crate::splash::splash_plugin,
+            crate::level::level_loader_plugin,
+            crate::audio::audio_plugin,
+            crate::settings::settings_plugin,
+            crate::starfield::starfield_plugin,
+            crate::background::background_plugin,
+            crate::graphics::graphics_plugin,
+            crate::theme::theme_plugin,
+            crate::localization::localization_plugin,
+        ))
+        .insert_resource(Client {
+            socket,
+            server: address,
+            player: None,
+            snapshots: VecDeque::new(),
+            partial: None,
+            entities: HashMap::new(),
+            last_heard: 0.0,
+            match_ended: false,
+        })
+        .add_systems(PreUpdate, receive)
+        .add_systems(OnEnter(GameState::StartMenu), display_waiting)
+        .add_systems(OnEnter(GameState::Game), display_pilots)
+        .add_systems(
+            Update,
+            (
+                (say_hello, enter_game).run_if(in_state(GameState::StartMenu)),
+                (send_input, interpolate, update_pilots, leave_game)
+                    .run_if(in_state(GameState::Game)),
+            ),
+        )
+        .run();
+}
+
+#[derive(Resource)]
+struct Client {
+    socket: UdpSocket,
+    server: SocketAddr,
+    player: Option<u8>,
+    snapshots: VecDeque<(f32, Snapshot)>,
+    // The snapshot being put back together, with the parts received so far
+    partial: Option<(Snapshot, Vec<u8>)>,
+    entities: HashMap<u64, Entity>,
+    last_heard: f32,
+    match_ended: bool,
+}
+
+impl Client {
+    fn send(&self, message: &ClientMessage) {
+        if let Err(error) = self.socket.send_to(&message.encode(), self.server) {
+            warn!("Could not send to {}: {error}", self.server);
+        }
+    }
+
+    // Returns the whole snapshot once all of its parts have arrived. Parts of an
+    // older tick are dropped, and a newer tick replaces an incomplete one
+    fn assemble(&mut self, part: u8, parts: u8, snapshot: Snapshot) -> Option<Snapshot> {
+        match &mut self.partial {
+            Some((partial, _)) if partial.tick > snapshot.tick => return None,
+            Some((partial, received)) if partial.tick == snapshot.tick => {
+                if received.contains(&part) {
+                    return None;
+                }
+                received.push(part);
+                partial.entities.extend(snapshot.entities);
+            }
+            _ => self.partial = Some((snapshot, vec![part])),
+        }
+        if self
+            .partial
+            .as_ref()
+            .is_some_and(|(_, received)| received.len() >= parts as usize)
+        {
+            self.partial.take().map(|(snapshot, _)| snapshot)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Component)]
+struct PilotsText;
+
+fn receive(mut client: ResMut<Client>, time: Res<Time>, mut audio: EventWriter<AudioStart>) {
+    let mut buffer = [0; 65536];
+    loop {
+        let length = match client.socket.recv_from(&mut buffer) {
+            Ok((length, address)) if address == client.server => length,
+            Ok(_) => continue,
+            Err(error) if error.kind() == ErrorKind::WouldBlock => break,
+            Err(error) => {
+                warn!("Could not receive: {error}");
+                break;
+            }
+        };
+        let message = ServerMessage::decode(&buffer[..length]);
+        if message.is_ok() {
+            client.last_heard = time.elapsed_secs();
+        }
+        match message {
+            Ok(ServerMessage::Welcome { player }) => {
+                if client.player.is_none() {
+                    info!("Joined as player {}", player + 1);
+                }
+                client.player = Some(player);
+            }
+            // Snapshots still in flight from a match that was left are dropped
+            Ok(ServerMessage::Snapshot {
+                part,
+                parts,
+                snapshot,
+            }) if client.player.is_some() => {
+                if let Some(snapshot) = client.assemble(part, parts, snapshot)
+                    && client
+                        .snapshots
+                        .back()
+                        .is_none_or(|(_, last)| last.tick < snapshot.tick)
+                {
+                    client.snapshots.push_back((time.elapsed_secs(), snapshot));
+                }
+            }
+            Ok(ServerMessage::Sound(sound)) => {
+                audio.write(sound);
+            }
+            Ok(ServerMessage::Snapshot { .. }) => (),
+            Ok(ServerMessage::MatchEnd) => client.match_ended = true,
+            Err(error) => warn!("Invalid message from server: {error}"),
+        }
+    }
+}
+
+// Repeated while waiting in the lobby, so the server keeps the slot and answers
+// with a welcome that shows it is still there
+fn say_hello(client: Res<Client>, time: Res<Time>, mut timer: Local<Option<Timer>>) {
+    let timer = timer.get_or_insert_with(|| Timer::from_seconds(0.5, TimerMode::Repeating));
+    if timer.tick(time.delta()).just_finished() || timer.elapsed_secs() == 0.0 {
+        client.send(&ClientMessage::Hello);
+    }
+}
+
+fn display_waiting(mut commands: Commands) {
+    commands.spawn((
+        Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        children![(
+            Text::default(),
+            Localized::new("net-waiting"),
+            TextFont {
+                font_size: 70.0,
+                ..default()
+            },
+        )],
+        StateScoped(GameState::StartMenu),
+    ));
+}
+
+// The match has started once the server sends its first snapshot
+fn enter_game(mut client: ResMut<Client>, mut next_state: ResMut<NextState<GameState>>) {
+    if client.player.is_some() && !client.snapshots.is_empty() {
+        client.match_ended = false;
+        next_state.set(GameState::Game);
+    }
+}
+
+// Back to waiting when the match is over or the server stopped answering, the
+// next hello joins the following match
+fn leave_game(
+    mut client: ResMut<Client>,
+    time: Res<Time>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let timed_out = time.elapsed_secs() - client.last_heard > SERVER_TIMEOUT;
+    if !client.match_ended && !timed_out {
+        return;
+    }
+    if timed_out {
+        warn!("Lost connection to the server");
+    }
+    client.player = None;
+    client.match_ended = false;
+    client.snapshots.clear();
+    client.partial = None;
+    client.entities.clear();
+    next_state.set(GameState::StartMenu);
+}
+
+fn send_input(client: Res<Client>, keyboard: Res<ButtonInput<KeyCode>>, settings: Res<Settings>) {
+    let controls = &settings.controls[0];
+    let input = RemoteInput {
+        rotate: match (
+            keyboard.pressed(controls.rotate_left),
+            keyboard.pressed(controls.rotate_right),
+        ) {
+            (true, false) => 1.0,
+            (false, true) => -1.0,
+            _ => 0.0,
+        },
+        thrust: keyboard.pressed(controls.thrust),
+        fire: keyboard.pressed(controls.fire),
+        hyperspace: keyboard.pressed(controls.hyperspace),
+    };
+    client.send(&ClientMessage::Input(input));
+}
+
+fn interpolate(
+    mut commands: Commands,
+    mut client: ResMut<Client>,
+    mut transforms: Query<&mut Transform, Without<Camera2d>>,
+    mut camera: Query<&mut Transform, With<Camera2d>>,
+    game_assets: Res<GameAssets>,
+    theme: Res<Theme>,
+    time: Res<Time>,
+) -> Result {
+    let render_time = time.elapsed_secs() - INTERPOLATION_DELAY;
+    while client.snapshots.len() > 2 && client.snapshots[1].0 <= render_time {
+        client.snapshots.pop_front();
+    }
+    let Some(&(from_time, ref from)) = client.snapshots.front() else {
+        return Ok(());
+    };
+    let (to_time, to) = client
+        .snapshots
+        .get(1)
+        .map(|(time, snapshot)| (*time, snapshot))
+        .unwrap_or((from_time, from));
+    let t = if to_time > from_time {
+        ((render_time - from_time) / (to_time - from_time)).clamp(0.0, 1.0)
+    } else {
+        1.0
+    };
+
+    let previous = from
+        .entities
+        .iter()
+        .map(|entity| (entity.id, entity))
+        .collect::<HashMap<_, _>>();
+    let displayed = to
+        .entities
+        .iter()
+        .map(|entity| {
+            let entity = match previous.get(&entity.id) {
+                Some(previous) => NetEntity {
+                    position: previous.position.lerp(entity.position, t),
+                    rotation: Quat::from_rotation_z(previous.rotation)
+                        .slerp(Quat::from_rotation_z(entity.rotation), t)
+                        .to_euler(EulerRot::XYZ)
+                        .2,
+                    ..*entity
+                },
+                None => *entity,
+            };
+            (entity.id, entity)
+        })
+        .collect::<HashMap<_, _>>();
+
+    let own_ship = NetKind::Ship(client.player.unwrap_or_default());
+    if let Some(ship) = displayed.values().find(|entity| entity.kind == own_ship) {
+        camera.single_mut()?.translation = ship.position.extend(0.0);
+    }
+
+    display_entities(
+        &mut commands,
+        &mut client.entities,
+        &mut transforms,
+        &displayed,
+        &game_assets,
+        &theme,
+    )?;
+
+    Ok(())
+}
+
+pub(super) fn display_entities(
+    commands: &mut Commands,
+    entities: &mut HashMap<u64, Entity>,
+    transforms: &mut Query<&mut Transform, Without<Camera2d>>,
+    displayed: &HashMap<u64, NetEntity>,
+    game_assets: &GameAssets,
+    theme: &Theme,
+) -> Result {
+    entities.retain(|id, entity| {
+        let keep = displayed.contains_key(id);
+        if !keep {
+            commands.entity(*entity).despawn();
+        }
+        keep
+    });
+
+    for (id, net_entity) in displayed {
+        let transform = Transform::from_translation(net_entity.position.extend(0.0))
+            .with_rotation(Quat::from_rotation_z(net_entity.rotation))
+            .with_scale(Vec3::splat(net_entity.scale));
+
+        match entities.get(id) {
+            Some(entity) => *transforms.get_mut(*entity)? = transform,
+            None => {
+                let sprite = match net_entity.kind {
+                    NetKind::Ship(player) => Sprite {
+                        image: game_assets.player_ship.clone(),
+                        color: theme.players[player as usize],
+                        ..default()
+                    },
+                    NetKind::Asteroid => Sprite::from_image(game_assets.asteroid.clone()),
+                    NetKind::Laser => Sprite {
+                        image: game_assets.laser.clone(),
+                        color: theme.laser,
+                        ..default()
+                    },
+                    NetKind::Explosion => Sprite::from_image(game_assets.explosion.clone()),
+                };
+                let entity = commands
+                    .spawn((sprite, transform, StateScoped(GameState::Game)))
+                    .id();
+                entities.insert(*id, entity);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn display_pilots(mut commands: Commands) {
+    commands.spawn((Text::default(), PilotsText, StateScoped(GameState::Game)));
+}
+
+fn update_pilots(
+    client: Res<Client>,
+    mut text: Query<&mut Text, With<PilotsText>>,
+    locale: Locale,
+) -> Result {
+    let Some((_, snapshot)) = client.snapshots.back() else {
+        return Ok(());
+    };
+    text.single_mut()?.0 = pilots_text(&snapshot.pilots, &locale);
+    Ok(())
+}
+
+pub(super) fn pilots_text(pilots: &[(u32, u32)], locale: &Locale) -> String {
+    pilots
+        .iter()
+        .enumerate()
+        .map(|(player, (lives, score))| {
+            locale.format(
+                "net-pilot",
+                &[
+                    ("player", (player + 1).to_string()),
+                    ("lives", lives.to_string()),
+                    ("score", score.to_string()),
+                ],
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,475 @@
+use bevy::prelude::*;
+use thiserror::Error;
+
+use super::simulation::SimInput;
+use crate::audio::AudioStart;
+
+// Player indices from the network are checked against this before they are used
+pub const MAX_PLAYERS: u8 = 2;
+// Snapshots are split into parts of this many entities, so that each datagram
+// stays well under a typical MTU
+const ENTITIES_PER_PART: usize = 40;
+
+#[derive(Debug, Error)]
+pub enum ProtocolError {
+    #[error("Message is truncated")]
+    Truncated,
+    #[error("Unknown message type {0}")]
+    UnknownMessage(u8),
+    #[error("String is not valid UTF-8")]
+    InvalidString,
+    #[error("Value out of range")]
+    OutOfRange,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RemoteInput {
+    pub rotate: f32,
+    pub thrust: bool,
+    pub fire: bool,
+    pub hyperspace: bool,
+}
+
+pub enum ClientMessage {
+    Hello,
+    Input(RemoteInput),
+}
+
+pub enum ServerMessage {
+    Welcome {
+        player: u8,
+    },
+    // One part of a snapshot, the client puts the parts of a tick back together
+    Snapshot {
+        part: u8,
+        parts: u8,
+        snapshot: Snapshot,
+    },
+    Sound(AudioStart),
+    // The match is over, clients go back to waiting for the next one
+    MatchEnd,
+}
+
+pub enum PeerMessage {
+    Inputs {
+        start_frame: u32,
+        inputs: Vec<SimInput>,
+        received: u32,
+    },
+    Checksum {
+        frame: u32,
+        checksum: u64,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub tick: u32,
+    pub pilots: Vec<(u32, u32)>,
+    pub entities: Vec<NetEntity>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct NetEntity {
+    pub id: u64,
+    pub kind: NetKind,
+    pub position: Vec2,
+    pub rotation: f32,
+    pub scale: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetKind {
+    Ship(u8),
+    Asteroid,
+    Laser,
+    Explosion,
+}
+
+impl Snapshot {
+    pub fn into_parts(self) -> Vec<ServerMessage> {
+        let chunks = self.entities.chunks(ENTITIES_PER_PART).collect::<Vec<_>>();
+        let parts = chunks.len().clamp(1, u8::MAX as usize);
+        (0..parts)
+            .map(|part| ServerMessage::Snapshot {
+                part: part as u8,
+                parts: parts as u8,
+                snapshot: Snapshot {
+                    tick: self.tick,
+                    pilots: self.pilots.clone(),
+                    entities: chunks.get(part).map_or(vec![], |chunk| chunk.to_vec()),
+                },
+            })
+            .collect()
+    }
+}
+
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn u8(&mut self, value: u8) -> &mut Self {
+        self.0.push(value);
+        self
+    }
+
+    fn u32(&mut self, value: u32) -> &mut Self {
+        self.0.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    fn u64(&mut self, value: u64) -> &mut Self {
+        self.0.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    fn f32(&mut self, value: f32) -> &mut Self {
+        self.0.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    // Strings are limited to 255 bytes, cut before the character that crosses it
+    fn str(&mut self, value: &str) -> &mut Self {
+        let mut len = value.len().min(u8::MAX as usize);
+        while !value.is_char_boundary(len) {
+            len -= 1;
+        }
+        self.u8(len as u8);
+        self.0.extend_from_slice(&value.as_bytes()[..len]);
+        self
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn bytes<const N: usize>(&mut self) -> Result<[u8; N], ProtocolError> {
+        let (bytes, rest) = self
+            .0
+            .split_first_chunk::<N>()
+            .ok_or(ProtocolError::Truncated)?;
+        self.0 = rest;
+        Ok(*bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, ProtocolError> {
+        Ok(self.bytes::<1>()?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, ProtocolError> {
+        Ok(u32::from_le_bytes(self.bytes()?))
+    }
+
+    fn u64(&mut self) -> Result<u64, ProtocolError> {
+        Ok(u64::from_le_bytes(self.bytes()?))
+    }
+
+    fn f32(&mut self) -> Result<f32, ProtocolError> {
+        Ok(f32::from_le_bytes(self.bytes()?))
+    }
+
+    fn player(&mut self) -> Result<u8, ProtocolError> {
+        let player = self.u8()?;
+        if player < MAX_PLAYERS {
+            Ok(player)
+        } else {
+            Err(ProtocolError::OutOfRange)
+        }
+    }
+
+    fn str(&mut self) -> Result<String, ProtocolError> {
+        let len = self.u8()? as usize;
+        if self.0.len() < len {
+            return Err(ProtocolError::Truncated);
+        }
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        String::from_utf8(bytes.to_vec()).map_err(|_| ProtocolError::InvalidString)
+    }
+}
+
+impl ClientMessage {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut writer = Writer(vec![]);
+        match self {
+            ClientMessage::Hello => {
+                writer.u8(0);
+            }
+            ClientMessage::Input(input) => {
+                writer.u8(1).f32(input.rotate).u8(input.thrust as u8
+                    | (input.fire as u8) << 1
+                    | (input.hyperspace as u8) << 2);
+            }
+        }
+        writer.0
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, ProtocolError> {
+        let mut reader = Reader(bytes);
+        match reader.u8()? {
+            0 => Ok(ClientMessage::Hello),
+            1 => {
+                let rotate = reader.f32()?;
+                // Also rejects NaN, which would end up in the physics
+                if !(-1.0..=1.0).contains(&rotate) {
+                    return Err(ProtocolError::OutOfRange);
+                }
+                let buttons = reader.u8()?;
+                Ok(ClientMessage::Input(RemoteInput {
+                    rotate,
+                    thrust: buttons & 1 != 0,
+                    fire: buttons & 2 != 0,
+                    hyperspace: buttons & 4 != 0,
+                }))
+            }
+            other => Err(ProtocolError::UnknownMessage(other)),
+        }
+    }
+}
+
+impl ServerMessage {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut writer = Writer(vec![]);
+        match self {
+            ServerMessage::Welcome { player } => {
+                writer.u8(0).u8(*player);
+            }
+            ServerMessage::Snapshot {
+                part,
+                parts,
+                snapshot,
+            } => {
+                writer.u8(1).u8(*part).u8(*parts).u32(snapshot.tick);
+                writer.u8(snapshot.pilots.len() as u8);
+                for (lives, score) in &snapshot.pilots {
+                    writer.u32(*lives).u32(*score);
+                }
+                writer.u32(snapshot.entities.len() as u32);
+                for entity in &snapshot.entities {
+                    let (kind, player) = match entity.kind {
+                        NetKind::Ship(player) => (0, player),
+                        NetKind::Asteroid => (1, 0),
+                        NetKind::Laser => (2, 0),
+                        NetKind::Explosion => (3, 0),
+                    };
+                    writer
+                        .u64(entity.id)
+                        .u8(kind)
+                        .u8(player)
+                        .f32(entity.position.x)
+                        .f32(entity.position.y)
+                        .f32(entity.rotation)
+                        .f32(entity.scale);
+                }
+            }
+            ServerMessage::Sound(sound) => {
+                writer.u8(2).str(&sound.cue);
+                match sound.position {
+                    Some(position) => writer.u8(1).f32(position.x).f32(position.y),
+                    None => writer.u8(0),
+                };
+            }
+            ServerMessage::MatchEnd => {
+                writer.u8(3);
+            }
+        }
+        writer.0
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, ProtocolError> {
+        let mut reader = Reader(bytes);
+        match reader.u8()? {
+            0 => Ok(ServerMessage::Welcome {
+                player: reader.player()?,
+            }),
+            1 => {
+                let (part, parts) = (reader.u8()?, reader.u8()?);
+                if part >= parts {
+                    return Err(ProtocolError::OutOfRange);
+                }
+                let tick = reader.u32()?;
+                let pilot_count = reader.u8()?;
+                if pilot_count > MAX_PLAYERS {
+                    return Err(ProtocolError::OutOfRange);
+                }
+                let pilots = (0..pilot_count)
+                    .map(|_| Ok((reader.u32()?, reader.u32()?)))
+                    .collect::<Result<_, ProtocolError>>()?;
+                let entities = (0..reader.u32()?)
+                    .map(|_| {
+                        let id = reader.u64()?;
+                        let kind = match (reader.u8()?, reader.u8()?) {
+                            (0, player) if player < MAX_PLAYERS => NetKind::Ship(player),
+                            (0, _) => return Err(ProtocolError::OutOfRange),
+                            (1, _) => NetKind::Asteroid,
+                            (2, _) => NetKind::Laser,
+                            (3, _) => NetKind::Explosion,
+                            (other, _) => return Err(ProtocolError::UnknownMessage(other)),
+                        };
+                        Ok(NetEntity {
+                            id,
+                            kind,
+                            position: Vec2::new(reader.f32()?, reader.f32()?),
+                            rotation: reader.f32()?,
+                            scale: reader.f32()?,
+                        })
+                    })
+                    .collect::<Result<_, ProtocolError>>()?;
+                Ok(ServerMessage::Snapshot {
+                    part,
+                    parts,
+                    snapshot: Snapshot {
+                        tick,
+                        pilots,
+                        entities,
+                    },
+                })
+            }
+            2 => {
+                let cue = reader.str()?.into();
+                let position = match reader.u8()? {
+                    0 => None,
+                    _ => Some(Vec2::new(reader.f32()?, reader.f32()?)),
+                };
+                Ok(ServerMessage::Sound(AudioStart { cue, position }))
+            }
+            3 => Ok(ServerMessage::MatchEnd),
+            other => Err(ProtocolError::UnknownMessage(other)),
+        }
+    }
+}
+
+impl PeerMessage {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut writer = Writer(vec![]);
+        match self {
+            PeerMessage::Inputs {
+                start_frame,
+                inputs,
+                received,
+            } => {
+                writer
+                    .u8(0)
+                    .u32(*start_frame)
+                    .u32(*received)
+                    .u32(inputs.len() as u32);
+                for input in inputs {
+                    writer.u8(input.0);
+                }
+            }
+            PeerMessage::Checksum { frame, checksum } => {
+                writer.u8(1).u32(*frame).u64(*checksum);
+            }
+        }
+        writer.0
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, ProtocolError> {
+        let mut reader = Reader(bytes);
+        match reader.u8()? {
+            0 => {
+                let start_frame = reader.u32()?;
+                let received = reader.u32()?;
+                let inputs = (0..reader.u32()?)
+                    .map(|_| Ok(SimInput(reader.u8()?)))
+                    .collect::<Result<_, ProtocolError>>()?;
+                Ok(PeerMessage::Inputs {
+                    start_frame,
+                    inputs,
+                    received,
+                })
+            }
+            1 => Ok(PeerMessage::Checksum {
+                frame: reader.u32()?,
+                checksum: reader.u64()?,
+            }),
+            other => Err(ProtocolError::UnknownMessage(other)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entity(id: u64, kind: NetKind) -> NetEntity {
+        NetEntity {
+            id,
+            kind,
+            position: Vec2::ZERO,
+            rotation: 0.0,
+            scale: 1.0,
+        }
+    }
+
+    fn ship(player: u8) -> Vec<u8> {
+        Snapshot {
+            tick: 1,
+            pilots: vec![(2, 0)],
+            entities: vec![entity(1, NetKind::Ship(player))],
+        }
+        .into_parts()[0]
+            .encode()
+    }
+
+    #[test]
+    fn large_snapshots_are_split() {
+        let snapshot = Snapshot {
+            tick: 7,
+            pilots: vec![(2, 100), (1, 200)],
+            entities: (0..150).map(|id| entity(id, NetKind::Asteroid)).collect(),
+        };
+        let parts = snapshot.into_parts();
+        assert_eq!(parts.len(), 4);
+
+        let mut ids = vec![];
+        for (index, message) in parts.iter().enumerate() {
+            let bytes = message.encode();
+            assert!(bytes.len() <= 1200);
+            let Ok(ServerMessage::Snapshot {
+                part,
+                parts,
+                snapshot,
+            }) = ServerMessage::decode(&bytes)
+            else {
+                panic!("not a snapshot");
+            };
+            assert_eq!((part as usize, parts), (index, 4));
+            assert_eq!(snapshot.tick, 7);
+            ids.extend(snapshot.entities.iter().map(|entity| entity.id));
+        }
+        assert_eq!(ids, (0..150).collect::<Vec<_>>());
+
+        let empty = Snapshot {
+            tick: 8,
+            pilots: vec![],
+            entities: vec![],
+        };
+        assert_eq!(empty.into_parts().len(), 1);
+    }
+
+    #[test]
+    fn rejects_unknown_players() {
+        assert!(ServerMessage::decode(&ship(MAX_PLAYERS - 1)).is_ok());
+        assert!(ServerMessage::decode(&ship(MAX_PLAYERS)).is_err());
+        assert!(ServerMessage::decode(&ship(u8::MAX)).is_err());
+        assert!(
+            ServerMessage::decode(&ServerMessage::Welcome { player: u8::MAX }.encode()).is_err()
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_rotation() {
+        for rotate in [f32::NAN, f32::INFINITY, -2.0] {
+            let message = ClientMessage::Input(RemoteInput {
+                rotate,
+                ..default()
+            });
+            assert!(ClientMessage::decode(&message.encode()).is_err());
+        }
+        let message = ClientMessage::Input(RemoteInput {
+            rotate: -1.0,
+            ..default()
+        });
+        assert!(ClientMessage::decode(&message.encode()).is_ok());
+    }
+}
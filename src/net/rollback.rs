@@ -0,0 +1,603 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    io::ErrorKind,
+    net::{SocketAddr, UdpSocket},
+};
+
+use bevy::prelude::*;
+use thiserror::Error;
+
+use super::{
+    client::{display_entities, pilots_text},
+    protocol::PeerMessage,
+    simulation::{FRAME_RATE, SimInput, SimState},
+};
+use crate::{
+    GameAssets, GameState,
+    assists::{Assists, Toggle, aim_assist_at, auto_fire_at},
+    audio::AudioStart,
+    level::CurrentLevel,
+    localization::Locale,
+    settings::Settings,
+    theme::Theme,
+};
+
+const INPUT_DELAY: u32 = 2;
+const MAX_PREDICTION: u32 = 8;
+const CHECKSUM_INTERVAL: u32 = FRAME_RATE;
+const SEED: u64 = 0x5eed_a57e_401d;
+// Aim assist stops turning once the ship is this close to facing its target
+const AIM_ASSIST_DEADZONE: f32 = 0.05;
+
+#[derive(Debug, Error)]
+pub enum RollbackError {
+    #[error("Desync detected at frame {0}")]
+    Desync(u32),
+    #[error("Network error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+pub struct P2pSession {
+    local_player: usize,
+    socket: UdpSocket,
+    peer: SocketAddr,
+    state: SimState,
+    // States at the start of the frames before `state.frame`, oldest first
+    snapshots: VecDeque<SimState>,
+    local_inputs: Vec<SimInput>,
+    remote_inputs: Vec<SimInput>,
+    predicted_inputs: Vec<SimInput>,
+    confirmed: u32,
+    peer_received: u32,
+    checksums: HashMap<u32, u64>,
+    peer_checksums: HashMap<u32, u64>,
+    rollbacks: u32,
+}
+
+impl P2pSession {
+    pub fn new(local_player: usize, socket: UdpSocket, peer: SocketAddr, state: SimState) -> Self {
+        P2pSession {
+            local_player,
+            socket,
+            peer,
+            state,
+            snapshots: VecDeque::new(),
+            local_inputs: vec![SimInput::default(); INPUT_DELAY as usize],
+            remote_inputs: vec![],
+            predicted_inputs: vec![],
+            confirmed: 0,
+            peer_received: 0,
+            checksums: HashMap::new(),
+            peer_checksums: HashMap::new(),
+            rollbacks: 0,
+        }
+    }
+
+    pub fn state(&self) -> &SimState {
+        &self.state
+    }
+
+    fn inputs(&self, frame: u32) -> [SimInput; 2] {
+        let local = self.local_inputs[frame as usize];
+        // Predict that the remote player keeps doing what they last did
+        let remote = self
+            .remote_inputs
+            .get(frame as usize)
+            .or(self.remote_inputs.last())
+            .copied()
+            .unwrap_or_default();
+        if self.local_player == 0 {
+            [local, remote]
+        } else {
+            [remote, local]
+        }
+    }
+
+    fn state_at(&self, frame: u32) -> &SimState {
+        let oldest = self.state.frame - self.snapshots.len() as u32;
+        self.snapshots
+            .get((frame - oldest) as usize)
+            .unwrap_or(&self.state)
+    }
+
+    fn simulate(&mut self) {
+        let frame = self.state.frame;
+        let remote = self.inputs(frame)[1 - self.local_player];
+        self.predicted_inputs.truncate(frame as usize);
+        self.predicted_inputs.push(remote);
+
+        self.snapshots.push_back(self.state.clone());
+        if self.snapshots.len() > MAX_PREDICTION as usize + 1 {
+            self.snapshots.pop_front();
+        }
+        self.state.step(self.inputs(frame));
+    }
+
+    // Returns true if a new frame was simulated, false when waiting for the peer
+    pub fn advance(&mut self, local_input: SimInput) -> Result<bool, RollbackError> {
+        self.poll()?;
+        if self.state.frame >= self.remote_inputs.len() as u32 + MAX_PREDICTION {
+            return Ok(false);
+        }
+
+        self.local_inputs.push(local_input);
+        self.simulate();
+        self.send_inputs();
+        Ok(true)
+    }
+
+    pub fn poll(&mut self) -> Result<(), RollbackError> {
+        self.receive()?;
+        self.rollback();
+        self.confirm()?;
+        self.send_inputs();
+        Ok(())
+    }
+
+    fn receive(&mut self) -> Result<(), RollbackError> {
+        let mut buffer = [0; 1500];
+        loop {
+            let length = match self.socket.recv_from(&mut buffer) {
+                Ok((length, address)) if address == self.peer => length,
+                Ok(_) => continue,
+                Err(error) if error.kind() == ErrorKind::WouldBlock => return Ok(()),
+                // The peer is not listening yet
+                Err(error) if error.kind() == ErrorKind::ConnectionRefused => continue,
+                Err(error) => return Err(error.into()),
+            };
+            match PeerMessage::decode(&buffer[..length]) {
+                Ok(PeerMessage::Inputs {
+                    start_frame,
+                    inputs,
+                    received,
+                }) => {
+                    for (frame, input) in (start_frame..).zip(inputs) {
+                        if frame == self.remote_inputs.len() as u32 {
+                            self.remote_inputs.push(input);
+                        }
+                    }
+                    self.peer_received = self.peer_received.max(received);
+                }
+                Ok(PeerMessage::Checksum { frame, checksum }) => {
+                    self.peer_checksums.insert(frame, checksum);
+                    self.compare_checksums(frame)?;
+                }
+                Err(error) => warn!("Invalid message from {}: {error}", self.peer),
+            }
+        }
+    }
+
+    fn rollback(&mut self) {
+        let checked = (self.remote_inputs.len() as u32).min(self.state.frame);
+        let Some(mispredicted) = (self.confirmed..checked).find(|frame| {
+            self.predicted_inputs[*frame as usize] != self.remote_inputs[*frame as usize]
+        }) else {
+            return;
+        };
+
+        let current = self.state.frame;
+        let oldest = current - self.snapshots.len() as u32;
+        self.snapshots
+            .truncate((mispredicted - oldest) as usize + 1);
+        self.state = self.snapshots.pop_back().unwrap();
+        while self.state.frame < current {
+            self.simulate();
+        }
+        self.rollbacks += 1;
+    }
+
+    fn confirm(&mut self) -> Result<(), RollbackError> {
+        // The state at a frame is final once every input before it is known
+        let confirmed = (self.remote_inputs.len() as u32).min(self.state.frame);
+        for frame in self.confirmed + 1..=confirmed {
+            if frame % CHECKSUM_INTERVAL == 0 {
+                let checksum = self.state_at(frame).checksum();
+                self.checksums.insert(frame, checksum);
+                self.send(&PeerMessage::Checksum { frame, checksum });
+                self.compare_checksums(frame)?;
+            }
+        }
+        self.confirmed = self.confirmed.max(confirmed);
+        Ok(())
+    }
+
+    fn compare_checksums(&mut self, frame: u32) -> Result<(), RollbackError> {
+        let (Some(local), Some(remote)) =
+            (self.checksums.get(&frame), self.peer_checksums.get(&frame))
+        else {
+            return Ok(());
+        };
+        if local != remote {
+            return Err(RollbackError::Desync(frame));
+        }
+        self.checksums.remove(&frame);
+        self.peer_checksums.remove(&frame);
+        Ok(())
+    }
+
+    fn send_inputs(&self) {
+        let start_frame = self.peer_received.min(self.local_inputs.len() as u32);
+        self.send(&PeerMessage::Inputs {
+            start_frame,
+            inputs: self.local_inputs[start_frame as usize..].to_vec(),
+            received: self.remote_inputs.len() as u32,
+        });
+    }
+
+    fn send(&self, message: &PeerMessage) {
+        if let Err(error) = self.socket.send_to(&message.encode(), self.peer) {
+            // The peer may not be listening yet, inputs are resent every frame
+            if error.kind() != ErrorKind::ConnectionRefused {
+                warn!("Could not send to {}: {error}", self.peer);
+            }
+        }
+    }
+}
+
+// Rolls back and resimulates every frame to check that the simulation is
+// deterministic and that snapshots restore the whole state
+pub struct SyncTestSession {
+    state: SimState,
+    history: VecDeque<(SimState, [SimInput; 2])>,
+    check_distance: usize,
+}
+
+impl SyncTestSession {
+    pub fn new(state: SimState, check_distance: usize) -> Self {
+        SyncTestSession {
+            state,
+            history: VecDeque::new(),
+            check_distance: check_distance.max(1),
+        }
+    }
+
+    pub fn advance(&mut self, inputs: [SimInput; 2]) -> Result<(), RollbackError> {
+        self.history.push_back((self.state.clone(), inputs));
+        if self.history.len() > self.check_distance {
+            self.history.pop_front();
+        }
+        self.state.step(inputs);
+
+        let mut replay = self.history[0].0.clone();
+        for (_, inputs) in &self.history {
+            replay.step(*inputs);
+        }
+        if replay.checksum() != self.state.checksum() {
+            return Err(RollbackError::Desync(self.state.frame));
+        }
+        Ok(())
+    }
+}
+
+pub enum SessionConfig {
+    P2p {
+        socket: UdpSocket,
+        peer: SocketAddr,
+        local_player: usize,
+    },
+    SyncTest {
+        check_distance: usize,
+    },
+}
+
+#[derive(Resource)]
+struct PendingSession(Option<SessionConfig>);
+
+#[derive(Resource)]
+#[allow(clippy::large_enum_variant)]
+enum Session {
+    P2p(P2pSession),
+    SyncTest(SyncTestSession),
+}
+
+impl Session {
+    fn state(&self) -> &SimState {
+        match self {
+            Session::P2p(session) => session.state(),
+            Session::SyncTest(session) => &session.state,
+        }
+    }
+
+    fn local_player(&self) -> usize {
+        match self {
+            Session::P2p(session) => session.local_player,
+            Session::SyncTest(_) => 0,
+        }
+    }
+}
+
+// Set when the peers or the resimulation disagree on the state, the session
+// stops instead of playing on in a different game
+#[derive(Resource)]
+struct Desynced(u32);
+
+#[derive(Resource, Default)]
+struct DisplayedEntities(HashMap<u64, Entity>);
+
+#[derive(Component)]
+struct SessionText;
+
+pub fn run_rollback(config: SessionConfig) {
+    App::new()
+        .add_plugins(DefaultPlugins.set(WindowPlugin {
+            primary_window: Some(Window {
+                title: "Bevy Workshop".into(),
+                ..default()
+            }),
+            ..default()
+        }))
+        .insert_resource(Time::<Fixed>::from_hz(FRAME_RATE as f64))
+        .init_state::<GameState>()
+        .enable_state_scoped_entities::<GameState>()
+        .add_plugins(bevy_enoki::EnokiPlugin)
+        .add_plugins((
+            crate::splash::splash_plugin,
+            crate::level::level_loader_plugin,
+            crate::audio::audio_plugin,
+            crate::settings::settings_plugin,
+            crate::starfield::starfield_plugin,
+            crate::background::background_plugin,
+            crate::graphics::graphics_plugin,
+            crate::theme::theme_plugin,
+            crate::localization::localization_plugin,
+        ))
+        .insert_resource(PendingSession(Some(config)))
+        .init_resource::<DisplayedEntities>()
+        .add_systems(Update, start_game.run_if(in_state(GameState::StartMenu)))
+        .add_systems(OnEnter(GameState::Game), start_session)
+        .add_systems(
+            FixedUpdate,
+            advance_session.run_if(in_state(GameState::Game).and(not(resource_exists::<Desynced>))),
+        )
+        .add_systems(Update, display_session.run_if(in_state(GameState::Game)))
+        .run();
+}
+
+fn start_game(mut next_state: ResMut<NextState<GameState>>) {
+    next_state.set(GameState::Game);
+}
+
+fn start_session(
+    mut commands: Commands,
+    mut pending: ResMut<PendingSession>,
+    level: CurrentLevel,
+) -> Result {
+    let state = SimState::new(level.get(), SEED);
+    let session = match pending.0.take().ok_or("Rollback session already started")? {
+        SessionConfig::P2p {
+            socket,
+            peer,
+            local_player,
+        } => Session::P2p(P2pSession::new(local_player, socket, peer, state)),
+        SessionConfig::SyncTest { check_distance } => {
+            Session::SyncTest(SyncTestSession::new(state, check_distance))
+        }
+    };
+    commands.insert_resource(session);
+    commands.remove_resource::<Desynced>();
+    commands.spawn((Text::default(), SessionText, StateScoped(GameState::Game)));
+    Ok(())
+}
+
+// Assists change what the local player sends instead of the simulation, so
+// both peers still simulate the same game. Game speed and infinite lives don't
+// apply to versus.
+fn assisted_input(
+    mut input: SimInput,
+    assists: &Assists,
+    aim: Option<(f32, f32)>,
+    thrust_toggle: &mut Toggle,
+) -> SimInput {
+    if assists.toggle_thrust {
+        let thrust = thrust_toggle.press(input.pressed(SimInput::THRUST));
+        input.set(SimInput::THRUST, thrust);
+    }
+    let Some((angle, distance)) = aim else {
+        return input;
+    };
+    if assists.auto_fire && auto_fire_at(angle, distance) {
+        input.set(SimInput::FIRE, true);
+    }
+    let turning = input.pressed(SimInput::LEFT) || input.pressed(SimInput::RIGHT);
+    if assists.aim_assist && !turning && aim_assist_at(angle) && angle.abs() > AIM_ASSIST_DEADZONE {
+        input.set(
+            if angle > 0.0 {
+                SimInput::LEFT
+            } else {
+                SimInput::RIGHT
+            },
+            true,
+        );
+    }
+    input
+}
+
+fn advance_session(
+    mut commands: Commands,
+    mut session: ResMut<Session>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut audio: EventWriter<AudioStart>,
+    settings: Res<Settings>,
+    mut thrust_toggles: Local<[Toggle; 2]>,
+) -> Result {
+    if session.state().winner().is_some() {
+        return Ok(());
+    }
+
+    // A peer always plays with the first player's controls
+    let mut local_input = |controls: usize, player: usize, state: &SimState| {
+        assisted_input(
+            SimInput::from_keyboard(&keyboard, &settings.controls[controls]),
+            &settings.assists,
+            state.aim(player),
+            &mut thrust_toggles[controls],
+        )
+    };
+    let local_player = session.local_player();
+    let result = match session.as_mut() {
+        Session::P2p(session) => {
+            let input = local_input(0, local_player, session.state());
+            session.advance(input)
+        }
+        Session::SyncTest(session) => {
+            let inputs = [0, 1].map(|player| local_input(player, player, &session.state));
+            session.advance(inputs).map(|()| true)
+        }
+    };
+    let advanced = match result {
+        Ok(advanced) => advanced,
+        Err(RollbackError::Desync(frame)) => {
+            error!("Desync at frame {frame} as player {}", local_player + 1);
+            commands.insert_resource(Desynced(frame));
+            return Ok(());
+        }
+        Err(error) => return Err(error.into()),
+    };
+
+    // Sounds are only played the first time a frame is simulated, not when resimulating
+    if advanced {
+        audio.write_batch(session.state().sounds.iter().cloned());
+    }
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn display_session(
+    mut commands: Commands,
+    session: Res<Session>,
+    mut displayed: ResMut<DisplayedEntities>,
+    mut transforms: Query<&mut Transform, Without<Camera2d>>,
+    mut camera: Query<&mut Transform, With<Camera2d>>,
+    mut text: Query<&mut Text, With<SessionText>>,
+    desynced: Option<Res<Desynced>>,
+    game_assets: Res<GameAssets>,
+    theme: Res<Theme>,
+    locale: Locale,
+) -> Result {
+    let state = session.state();
+    let entities = state
+        .entities()
+        .into_iter()
+        .map(|entity| (entity.id, entity))
+        .collect::<HashMap<_, _>>();
+
+    if let Some(ship) = entities.get(&(session.local_player() as u64)) {
+        camera.single_mut()?.translation = ship.position.extend(0.0);
+    }
+    display_entities(
+        &mut commands,
+        &mut displayed.0,
+        &mut transforms,
+        &entities,
+        &game_assets,
+        &theme,
+    )?;
+
+    let mut status = pilots_text(&state.pilots(), &locale);
+    if let Some(desynced) = desynced {
+        status.push('\n');
+        status.push_str(&locale.format("net-desync", &[("frame", desynced.0.to_string())]));
+    } else if let Some(winner) = state.winner() {
+        status.push('\n');
+        status.push_str(&locale.format("won-player", &[("player", (winner + 1).to_string())]));
+    }
+    text.single_mut()?.0 = status;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::Level;
+
+    const FRAMES: u32 = 1800;
+
+    fn level() -> Level {
+        std::fs::read_to_string("assets/level.bw")
+            .unwrap()
+            .parse()
+            .unwrap()
+    }
+
+    fn random_inputs(mut seed: u64) -> impl FnMut() -> u64 {
+        move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        }
+    }
+
+    // Two peers in the same process over loopback sockets, with random inputs
+    // and irregular frame pacing so that predictions fail and rollbacks happen
+    #[test]
+    fn loopback_peers_agree() {
+        let level = level();
+        let sockets = [
+            UdpSocket::bind("127.0.0.1:0").unwrap(),
+            UdpSocket::bind("127.0.0.1:0").unwrap(),
+        ];
+        for socket in &sockets {
+            socket.set_nonblocking(true).unwrap();
+        }
+        let addresses = sockets
+            .each_ref()
+            .map(|socket| socket.local_addr().unwrap());
+        let [first, second] = sockets;
+        let mut sessions = [
+            P2pSession::new(0, first, addresses[1], SimState::new(&level, SEED)),
+            P2pSession::new(1, second, addresses[0], SimState::new(&level, SEED)),
+        ];
+
+        let mut random = random_inputs(0x2545_f491_4f6c_dd1d);
+        let mut inputs = [SimInput::default(); 2];
+        for _ in 0..FRAMES * 20 {
+            for (player, session) in sessions.iter_mut().enumerate() {
+                if random().is_multiple_of(3) {
+                    continue;
+                }
+                if random().is_multiple_of(10) {
+                    inputs[player] = SimInput(random() as u8 & 0b11111);
+                }
+                if session.state().frame < FRAMES {
+                    session.advance(inputs[player]).unwrap();
+                } else {
+                    session.poll().unwrap();
+                }
+            }
+            if sessions
+                .iter()
+                .all(|session| session.state().frame == FRAMES && session.confirmed == FRAMES)
+            {
+                break;
+            }
+        }
+
+        for session in &sessions {
+            assert_eq!(
+                session.confirmed, FRAMES,
+                "peers did not reach the last frame"
+            );
+            assert!(session.rollbacks > 0, "no rollback was exercised");
+        }
+        assert_eq!(
+            sessions[0].state().checksum(),
+            sessions[1].state().checksum()
+        );
+    }
+
+    #[test]
+    fn resimulation_matches() {
+        let mut session = SyncTestSession::new(SimState::new(&level(), SEED), 7);
+        let mut random = random_inputs(0x9e37_79b9_7f4a_7c15);
+        let mut inputs = [SimInput::default(); 2];
+        for _ in 0..FRAMES {
+            if random().is_multiple_of(10) {
+                inputs = [0, 1].map(|_| SimInput(random() as u8 & 0b11111));
+            }
+            if let Err(error) = session.advance(inputs) {
+                panic!("{error}");
+            }
+        }
+    }
+}
@@ -0,0 +1,364 @@
+use std::{
+    io::ErrorKind,
+    net::{SocketAddr, UdpSocket},
+    time::Duration,
+};
+
+use avian2d::{PhysicsPlugins, prelude::Gravity};
+use bevy::{
+    app::ScheduleRunnerPlugin,
+    prelude::*,
+    render::{RenderPlugin, settings::WgpuSettings},
+    window::ExitCondition,
+    winit::WinitPlugin,
+};
+use bevy_enhanced_input::prelude::*;
+use bevy_enoki::{Particle2dEffect, prelude::ParticleEffectLoader};
+
+use super::protocol::{ClientMessage, NetEntity, NetKind, RemoteInput, ServerMessage, Snapshot};
+use crate::{
+    GameMode, GameState,
+    audio::AudioStart,
+    camera::Impact,
+    effects::Effect,
+    game::{
+        Asteroid, Explosion, FireLaser, Hyperspace, Laser, Pilots, Player, RespawnRequest, Rotate,
+        Thrust,
+    },
+};
+
+const CLIENT_TIMEOUT: f32 = 5.0;
+
+pub fn run_server(port: u16, game_mode: GameMode) {
+    let socket = UdpSocket::bind(("0.0.0.0", port)).expect("Could not bind server socket");
+    socket.set_nonblocking(true).unwrap();
+    info!("Listening on port {port} for {game_mode:?}");
+
+    App::new()
+        .add_plugins(
+            DefaultPlugins
+                .set(WindowPlugin {
+                    primary_window: None,
+                    exit_condition: ExitCondition::DontExit,
+                    close_when_requested: false,
+                })
+                .set(RenderPlugin {
+                    render_creation: WgpuSettings {
+                        backends: None,
+                        ..default()
+                    }
+                    .into(),
+                    ..default()
+                })
+                .disable::<WinitPlugin>(),
+        )
+        .add_plugins(ScheduleRunnerPlugin::run_loop(Duration::from_secs_f64(
+            1.0 / 60.0,
+        )))
+        .init_state::<GameState>()
+        .insert_resource(game_mode)
+        .enable_state_scoped_entities::<GameState>()
+        .add_plugins((PhysicsPlugins::default(), EnhancedInputPlugin))
+        .init_asset::<Particle2dEffect>()
+        .init_asset_loader::<ParticleEffectLoader>()
+        .insert_resource(Gravity::ZERO)
+        .init_resource::<crate::settings::Settings>()
+        .init_resource::<crate::theme::Theme>()
+        .add_event::<AudioStart>()
+        .add_plugins(crate::audio::sound_bank_plugin)
+        .add_event::<Impact>()
+        .add_event::<Effect>()
+        .add_plugins((
+            crate::splash::splash_plugin,
+            crate::game::game_plugin,
+            crate::level::level_loader_plugin,
+            crate::waves::waves_plugin,
+            crate::high_scores::high_scores_plugin,
+            crate::objectives::objectives_plugin,
+            crate::localization::localization_plugin,
+        ))
+        .insert_resource(Server {
+            socket,
+            clients: vec![None; game_mode.player_count()],
+            tick: 0,
+        })
+        .insert_resource(SnapshotTimer(Timer::from_seconds(
+            1.0 / 20.0,
+            TimerMode::Repeating,
+        )))
+        .add_systems(PreUpdate, (receive, drop_stale_clients).chain())
+        .add_systems(OnExit(GameState::Game), end_match)
+        .add_systems(
+            Update,
+            (
+                start_match.run_if(in_state(GameState::StartMenu).or(in_state(GameState::Won))),
+                apply_remote_inputs.run_if(in_state(GameState::Game)),
+            ),
+        )
+        .add_systems(
+            PostUpdate,
+            (
+                send_snapshot.run_if(in_state(GameState::Game)),
+                forward_sounds,
+            ),
+        )
+        .run();
+}
+
+#[derive(Clone)]
+struct RemoteClient {
+    address: SocketAddr,
+    input: RemoteInput,
+    previous_input: RemoteInput,
+    last_seen: f32,
+}
+
+#[derive(Resource)]
+struct Server {
+    socket: UdpSocket,
+    clients: Vec<Option<RemoteClient>>,
+    tick: u32,
+}
+
+impl Server {
+    fn send(&self, address: SocketAddr, message: &ServerMessage) {
+        if let Err(error) = self.socket.send_to(&message.encode(), address) {
+            warn!("Could not send to {address}: {error}");
+        }
+    }
+
+    fn broadcast(&self, message: &ServerMessage) {
+        let bytes = message.encode();
+        for client in self.clients.iter().flatten() {
+            if let Err(error) = self.socket.send_to(&bytes, client.address) {
+                warn!("Could not send to {}: {error}", client.address);
+            }
+        }
+    }
+}
+
+#[derive(Resource)]
+struct SnapshotTimer(Timer);
+
+fn receive(mut server: ResMut<Server>, time: Res<Time>) {
+    let mut buffer = [0; 1500];
+    loop {
+        let (length, address) = match server.socket.recv_from(&mut buffer) {
+            Ok(received) => received,
+            Err(error) if error.kind() == ErrorKind::WouldBlock => break,
+            Err(error) => {
+                warn!("Could not receive: {error}");
+                break;
+            }
+        };
+        let message = match ClientMessage::decode(&buffer[..length]) {
+            Ok(message) => message,
+            Err(error) => {
+                warn!("Invalid message from {address}: {error}");
+                continue;
+            }
+        };
+
+        let slot = server
+            .clients
+            .iter()
+            .position(|client| client.as_ref().is_some_and(|c| c.address == address));
+        match (message, slot) {
+            (ClientMessage::Hello, Some(player)) => {
+                server.clients[player].as_mut().unwrap().last_seen = time.elapsed_secs();
+                server.send(
+                    address,
+                    &ServerMessage::Welcome {
+                        player: player as u8,
+                    },
+                );
+            }
+            (ClientMessage::Hello, None) => {
+                let Some(player) = server.clients.iter().position(Option::is_none) else {
+                    continue;
+                };
+                info!("Player {} joined from {address}", player + 1);
+                server.clients[player] = Some(RemoteClient {
+                    address,
+                    input: RemoteInput::default(),
+                    previous_input: RemoteInput::default(),
+                    last_seen: time.elapsed_secs(),
+                });
+                server.send(
+                    address,
+                    &ServerMessage::Welcome {
+                        player: player as u8,
+                    },
+                );
+            }
+            (ClientMessage::Input(input), Some(player)) => {
+                let client = server.clients[player].as_mut().unwrap();
+                client.input = input;
+                client.last_seen = time.elapsed_secs();
+            }
+            (ClientMessage::Input(_), None) => {}
+        }
+    }
+}
+
+fn drop_stale_clients(
+    mut server: ResMut<Server>,
+    time: Res<Time>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for (player, slot) in server.clients.iter_mut().enumerate() {
+        if slot
+            .as_ref()
+            .is_some_and(|client| time.elapsed_secs() - client.last_seen > CLIENT_TIMEOUT)
+        {
+            info!("Player {} timed out", player + 1);
+            *slot = None;
+            // The match can't go on without them, wait for a full lobby again
+            if *state.get() == GameState::Game {
+                next_state.set(GameState::StartMenu);
+            }
+        }
+    }
+}
+
+fn end_match(server: Res<Server>) {
+    server.broadcast(&ServerMessage::MatchEnd);
+}
+
+fn start_match(server: Res<Server>, mut next_state: ResMut<NextState<GameState>>) {
+    if server.clients.iter().all(Option::is_some) {
+        next_state.set(GameState::Game);
+    }
+}
+
+fn apply_remote_inputs(
+    mut commands: Commands,
+    mut server: ResMut<Server>,
+    ships: Query<(Entity, &Player)>,
+    mut requests: EventWriter<RespawnRequest>,
+) {
+    for (player, client) in server.clients.iter_mut().enumerate() {
+        let Some(client) = client else {
+            continue;
+        };
+        let input = client.input;
+        let previous = std::mem::replace(&mut client.previous_input, input);
+
+        let Some((ship, _)) = ships.iter().find(|(_, ship)| ship.0 == player) else {
+            if input.fire && !previous.fire {
+                requests.write(RespawnRequest(player));
+            }
+            continue;
+        };
+
+        if input.rotate != 0.0 {
+            commands.trigger_targets(
+                Fired::<Rotate> {
+                    value: input.rotate,
+                    state: ActionState::Fired,
+                    fired_secs: 0.0,
+                    elapsed_secs: 0.0,
+                },
+                ship,
+            );
+        }
+        if input.thrust {
+            commands.trigger_targets(
+                Fired::<Thrust> {
+                    value: true,
+                    state: ActionState::Fired,
+                    fired_secs: 0.0,
+                    elapsed_secs: 0.0,
+                },
+                ship,
+            );
+        } else if previous.thrust {
+            commands.trigger_targets(
+                Completed::<Thrust> {
+                    value: false,
+                    state: ActionState::None,
+                    fired_secs: 0.0,
+                    elapsed_secs: 0.0,
+                },
+                ship,
+            );
+        }
+        if input.fire {
+            commands.trigger_targets(
+                Fired::<FireLaser> {
+                    value: true,
+                    state: ActionState::Fired,
+                    fired_secs: 0.0,
+                    elapsed_secs: 0.0,
+                },
+                ship,
+            );
+        }
+        if input.hyperspace && !previous.hyperspace {
+            commands.trigger_targets(
+                Started::<Hyperspace> {
+                    value: true,
+                    state: ActionState::Fired,
+                },
+                ship,
+            );
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn send_snapshot(
+    mut server: ResMut<Server>,
+    mut timer: ResMut<SnapshotTimer>,
+    time: Res<Time>,
+    entities: Query<
+        (
+            Entity,
+            &Transform,
+            Option<&Player>,
+            Has<Asteroid>,
+            Has<Laser>,
+        ),
+        Or<(With<Player>, With<Asteroid>, With<Laser>, With<Explosion>)>,
+    >,
+    pilots: Res<Pilots>,
+) {
+    if !timer.0.tick(time.delta()).just_finished() {
+        return;
+    }
+    server.tick += 1;
+
+    let snapshot = Snapshot {
+        tick: server.tick,
+        pilots: pilots
+            .0
+            .iter()
+            .map(|pilot| (pilot.lives, pilot.score))
+            .collect(),
+        entities: entities
+            .iter()
+            .map(|(entity, transform, player, asteroid, laser)| NetEntity {
+                id: entity.to_bits(),
+                kind: match (player, asteroid, laser) {
+                    (Some(player), _, _) => NetKind::Ship(player.0 as u8),
+                    (_, true, _) => NetKind::Asteroid,
+                    (_, _, true) => NetKind::Laser,
+                    _ => NetKind::Explosion,
+                },
+                position: transform.translation.xy(),
+                rotation: transform.rotation.to_euler(EulerRot::XYZ).2,
+                scale: transform.scale.x,
+            })
+            .collect(),
+    };
+    for part in snapshot.into_parts() {
+        server.broadcast(&part);
+    }
+}
+
+fn forward_sounds(server: Res<Server>, mut sounds: EventReader<AudioStart>) {
+    for sound in sounds.read() {
+        server.broadcast(&ServerMessage::Sound(sound.clone()));
+    }
+}
@@ -0,0 +1,755 @@
+use bevy::prelude::*;
+
+use super::protocol::{NetEntity, NetKind};
+use crate::{
+    assists,
+    audio::AudioStart,
+    game::{
+        ASTEROID_CLEARANCE, ASTEROID_DENSITY, ASTEROID_RADIUS, ASTEROID_SIZE, ASTEROID_SPEED,
+        EXPLOSION_DURATION, FIRE_COOLDOWN, HYPERSPACE_DURATION, INVULNERABILITY_DURATION,
+        LASER_LIFETIME, LASER_OFFSET, LASER_SHIP_DAMAGE, LASER_SPEED, MAX_SPEED,
+        NEAREST_PULL_DISTANCE, NEAREST_PULL_SPEED, ROTATION_RATE, SHIP_ANGULAR_DAMPING,
+        SHIP_KILL_SCORE, SHIP_RADIUS, Shield, THRUST, absorb_hit, asteroid_health,
+        asteroid_impact_damage, asteroid_score, find_safe_respawn, hyperspace_scale,
+        ship_impact_damage, spawn_point,
+    },
+    level::{Level, ShieldSettings},
+};
+
+// The rollback simulation plays versus by the rules in game.rs, but only uses
+// IEEE operations that are exactly rounded (no trigonometry, no physics
+// engine), so both peers compute bit-identical states from the same inputs.
+
+pub const FRAME_RATE: u32 = 60;
+const DELTA: f32 = 1.0 / FRAME_RATE as f32;
+const PLAYERS: usize = 2;
+
+const fn frames(seconds: f32) -> u32 {
+    (seconds * FRAME_RATE as f32) as u32
+}
+
+const LASER_FRAMES: u32 = frames(LASER_LIFETIME);
+const FIRE_COOLDOWN_FRAMES: u32 = frames(FIRE_COOLDOWN);
+const EXPLOSION_FRAMES: u32 = frames(EXPLOSION_DURATION);
+const INVULNERABLE_FRAMES: u32 = frames(INVULNERABILITY_DURATION);
+const HYPERSPACE_FRAMES: u32 = frames(HYPERSPACE_DURATION);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SimInput(pub u8);
+
+impl SimInput {
+    pub const LEFT: u8 = 1;
+    pub const RIGHT: u8 = 2;
+    pub const THRUST: u8 = 4;
+    pub const FIRE: u8 = 8;
+    pub const HYPERSPACE: u8 = 16;
+
+    pub fn from_keyboard(
+        keyboard: &ButtonInput<KeyCode>,
+        controls: &crate::game::Controls,
+    ) -> Self {
+        let mut input = 0;
+        for (key, button) in [
+            (controls.rotate_left, Self::LEFT),
+            (controls.rotate_right, Self::RIGHT),
+            (controls.thrust, Self::THRUST),
+            (controls.fire, Self::FIRE),
+            (controls.hyperspace, Self::HYPERSPACE),
+        ] {
+            if keyboard.pressed(key) {
+                input |= button;
+            }
+        }
+        SimInput(input)
+    }
+
+    pub fn pressed(self, button: u8) -> bool {
+        self.0 & button != 0
+    }
+
+    pub fn set(&mut self, button: u8, pressed: bool) {
+        if pressed {
+            self.0 |= button;
+        } else {
+            self.0 &= !button;
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct SimState {
+    pub frame: u32,
+    rng: u64,
+    next_id: u64,
+    settings: SimSettings,
+    ships: [SimShip; PLAYERS],
+    asteroids: Vec<SimAsteroid>,
+    lasers: Vec<SimLaser>,
+    explosions: Vec<SimExplosion>,
+    // Shared by both ships, like the Ammo resource
+    ammo: Option<u32>,
+    pub sounds: Vec<AudioStart>,
+}
+
+#[derive(Clone, Copy)]
+struct SimSettings {
+    half_size: Vec2,
+    shield: Option<ShieldSettings>,
+    hyperspace_cooldown: u32,
+    hyperspace_risk: f32,
+}
+
+#[derive(Clone)]
+struct SimShip {
+    id: u64,
+    alive: bool,
+    position: Vec2,
+    velocity: Vec2,
+    heading: Vec2,
+    angular_velocity: f32,
+    shield: Option<Shield>,
+    lives: u32,
+    score: u32,
+    eliminated: bool,
+    respawn_frames: u32,
+    invulnerable_frames: u32,
+    fire_cooldown: u32,
+    hyperspace_frames: u32,
+    hyperspace_cooldown: u32,
+    previous_input: SimInput,
+}
+
+#[derive(Clone)]
+struct SimAsteroid {
+    id: u64,
+    position: Vec2,
+    velocity: Vec2,
+    size: f32,
+    health: f32,
+    max_health: f32,
+}
+
+#[derive(Clone)]
+struct SimLaser {
+    id: u64,
+    position: Vec2,
+    velocity: Vec2,
+    frames: u32,
+    fired_by: usize,
+}
+
+#[derive(Clone)]
+struct SimExplosion {
+    id: u64,
+    position: Vec2,
+    frames: u32,
+}
+
+impl SimState {
+    pub fn new(level: &Level, seed: u64) -> Self {
+        let settings = SimSettings {
+            half_size: Vec2::new(level.width as f32, level.height as f32) / 2.0,
+            shield: level.shield,
+            hyperspace_cooldown: frames(level.hyperspace.cooldown),
+            hyperspace_risk: level.hyperspace.risk,
+        };
+        let mut state = SimState {
+            frame: 0,
+            rng: seed.max(1),
+            next_id: PLAYERS as u64,
+            settings,
+            ships: [0, 1].map(|player| SimShip {
+                id: player as u64,
+                alive: true,
+                position: spawn_point(player, PLAYERS),
+                velocity: Vec2::ZERO,
+                heading: Vec2::Y,
+                angular_velocity: 0.0,
+                shield: settings.shield.map(Shield::from),
+                lives: level.lives.saturating_sub(1),
+                score: 0,
+                eliminated: false,
+                respawn_frames: 0,
+                invulnerable_frames: 0,
+                fire_cooldown: 0,
+                hyperspace_frames: 0,
+                hyperspace_cooldown: 0,
+                previous_input: SimInput::default(),
+            }),
+            asteroids: vec![],
+            lasers: vec![],
+            explosions: vec![],
+            ammo: level.ammo,
+            sounds: vec![],
+        };
+
+        while state.asteroids.len() < level.asteroids as usize {
+            let position = state.random_position();
+            if position.length() < ASTEROID_CLEARANCE {
+                continue;
+            }
+            let velocity = state.random_direction()
+                * state.random_range(ASTEROID_SPEED.start, ASTEROID_SPEED.end);
+            let size = state.random_range(ASTEROID_SIZE.start, ASTEROID_SIZE.end);
+            let max_health = asteroid_health(size, 1.0);
+            let id = state.next_id();
+            state.asteroids.push(SimAsteroid {
+                id,
+                position,
+                velocity,
+                size,
+                health: max_health,
+                max_health,
+            });
+        }
+
+        state
+    }
+
+    fn next_id(&mut self) -> u64 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    fn random(&mut self) -> f32 {
+        // xorshift64
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        (self.rng >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    fn random_range(&mut self, min: f32, max: f32) -> f32 {
+        min + self.random() * (max - min)
+    }
+
+    fn random_position(&mut self) -> Vec2 {
+        let half_size = self.settings.half_size;
+        Vec2::new(
+            self.random_range(-half_size.x, half_size.x),
+            self.random_range(-half_size.y, half_size.y),
+        )
+    }
+
+    fn random_direction(&mut self) -> Vec2 {
+        loop {
+            let direction = Vec2::new(self.random_range(-1.0, 1.0), self.random_range(-1.0, 1.0));
+            let length_squared = direction.length_squared();
+            if length_squared > 0.01 && length_squared <= 1.0 {
+                return direction / length_squared.sqrt();
+            }
+        }
+    }
+
+    pub fn step(&mut self, inputs: [SimInput; PLAYERS]) {
+        self.sounds.clear();
+
+        for (player, input) in inputs.into_iter().enumerate() {
+            self.step_ship(player, input);
+        }
+
+        for asteroid in &mut self.asteroids {
+            asteroid.position += asteroid.velocity * DELTA;
+        }
+        for laser in &mut self.lasers {
+            laser.position += laser.velocity * DELTA;
+            laser.frames -= 1;
+        }
+        self.lasers.retain(|laser| laser.frames > 0);
+        for explosion in &mut self.explosions {
+            explosion.frames -= 1;
+        }
+        self.explosions.retain(|explosion| explosion.frames > 0);
+
+        self.collide_asteroids();
+        self.collide_ships();
+        self.collide_lasers();
+        self.pull_nearest_asteroids();
+
+        self.frame += 1;
+    }
+
+    fn step_ship(&mut self, player: usize, input: SimInput) {
+        let settings = self.settings;
+        let ship = &mut self.ships[player];
+        let previous_input = std::mem::replace(&mut ship.previous_input, input);
+        ship.fire_cooldown = ship.fire_cooldown.saturating_sub(1);
+        ship.hyperspace_cooldown = ship.hyperspace_cooldown.saturating_sub(1);
+        ship.invulnerable_frames = ship.invulnerable_frames.saturating_sub(1);
+
+        if !ship.alive {
+            self.step_wreck(player, input, previous_input);
+            return;
+        }
+
+        if let Some(shield) = &mut ship.shield {
+            shield.regenerate(DELTA);
+        }
+
+        if ship.hyperspace_frames > 0 {
+            ship.hyperspace_frames -= 1;
+            if ship.hyperspace_frames == HYPERSPACE_FRAMES / 2 {
+                if self.random() < settings.hyperspace_risk {
+                    self.destroy_ship(player);
+                    return;
+                }
+                let position = self.random_position();
+                let ship = &mut self.ships[player];
+                ship.position = position;
+                ship.velocity = Vec2::ZERO;
+            }
+            return;
+        }
+
+        if input.pressed(SimInput::LEFT) != input.pressed(SimInput::RIGHT) {
+            let rate = ROTATION_RATE * 60.0 * DELTA;
+            ship.angular_velocity += if input.pressed(SimInput::LEFT) {
+                rate
+            } else {
+                -rate
+            };
+        }
+        // Same damping as avian's AngularDamping
+        ship.angular_velocity /= 1.0 + DELTA * SHIP_ANGULAR_DAMPING;
+        ship.heading = rotate(ship.heading, ship.angular_velocity * DELTA);
+
+        if input.pressed(SimInput::THRUST) {
+            ship.velocity = (ship.velocity + ship.heading * THRUST).clamp_length_max(MAX_SPEED);
+        }
+        ship.position += ship.velocity * DELTA;
+
+        if input.pressed(SimInput::FIRE) && ship.fire_cooldown == 0 && self.ammo != Some(0) {
+            ship.fire_cooldown = FIRE_COOLDOWN_FRAMES;
+            if let Some(ammo) = &mut self.ammo {
+                *ammo -= 1;
+            }
+            let position = ship.position + ship.heading * LASER_OFFSET;
+            let velocity = ship.heading * LASER_SPEED;
+            let id = self.next_id();
+            self.lasers.push(SimLaser {
+                id,
+                position,
+                velocity,
+                frames: LASER_FRAMES,
+                fired_by: player,
+            });
+            self.sounds.push(AudioStart::at("laser", position));
+        }
+
+        // Like in game.rs, a ship can fire and jump on the same frame
+        let ship = &mut self.ships[player];
+        if input.pressed(SimInput::HYPERSPACE)
+            && !previous_input.pressed(SimInput::HYPERSPACE)
+            && ship.hyperspace_cooldown == 0
+        {
+            ship.hyperspace_frames = HYPERSPACE_FRAMES;
+            ship.hyperspace_cooldown = settings.hyperspace_cooldown;
+            self.sounds
+                .push(AudioStart::at("hyperspace", ship.position));
+        }
+    }
+
+    // Lives are counted once the explosion is over, then the pilot respawns
+    // near where they died as soon as it is clear of asteroids
+    fn step_wreck(&mut self, player: usize, input: SimInput, previous_input: SimInput) {
+        let ship = &mut self.ships[player];
+        if ship.respawn_frames > 0 {
+            ship.respawn_frames -= 1;
+            if ship.respawn_frames == 0 {
+                if ship.lives == 0 {
+                    ship.eliminated = true;
+                } else {
+                    ship.lives -= 1;
+                }
+            }
+            return;
+        }
+        if ship.eliminated
+            || !input.pressed(SimInput::FIRE)
+            || previous_input.pressed(SimInput::FIRE)
+        {
+            return;
+        }
+        let Some(position) = find_safe_respawn(
+            ship.position,
+            self.asteroids.iter().map(|asteroid| asteroid.position),
+            self.settings.half_size,
+        ) else {
+            return;
+        };
+
+        let ship = &mut self.ships[player];
+        ship.alive = true;
+        ship.position = position;
+        ship.velocity = Vec2::ZERO;
+        ship.heading = Vec2::Y;
+        ship.angular_velocity = 0.0;
+        ship.shield = self.settings.shield.map(Shield::from);
+        ship.invulnerable_frames = INVULNERABLE_FRAMES;
+        ship.fire_cooldown = 0;
+        ship.hyperspace_cooldown = 0;
+    }
+
+    fn destroy_ship(&mut self, player: usize) {
+        let ship = &mut self.ships[player];
+        ship.alive = false;
+        ship.respawn_frames = EXPLOSION_FRAMES;
+        ship.hyperspace_frames = 0;
+        let position = ship.position;
+        self.spawn_explosion(position);
+        self.sounds.push(AudioStart::at("ship_explosion", position));
+    }
+
+    fn absorb_hit(&mut self, player: usize, damage: f32) -> bool {
+        let ship = &mut self.ships[player];
+        absorb_hit(ship.shield.as_mut(), ship.invulnerable_frames > 0, damage)
+    }
+
+    fn spawn_explosion(&mut self, position: Vec2) {
+        let id = self.next_id();
+        self.explosions.push(SimExplosion {
+            id,
+            position,
+            frames: EXPLOSION_FRAMES,
+        });
+    }
+
+    fn collide_asteroids(&mut self) {
+        for i in 0..self.asteroids.len() {
+            for j in i + 1..self.asteroids.len() {
+                let (left, right) = self.asteroids.split_at_mut(j);
+                let (a, b) = (&mut left[i], &mut right[0]);
+                let Some(impact_speed) = bounce(
+                    (&mut a.position, &mut a.velocity, asteroid_mass(a.size)),
+                    (&mut b.position, &mut b.velocity, asteroid_mass(b.size)),
+                    ASTEROID_RADIUS * (a.size + b.size),
+                ) else {
+                    continue;
+                };
+                if let Some(damage) = asteroid_impact_damage(impact_speed) {
+                    a.health -= damage;
+                    b.health -= damage;
+                }
+            }
+        }
+
+        let destroyed = self
+            .asteroids
+            .iter()
+            .filter(|asteroid| asteroid.health <= 0.0)
+            .map(|asteroid| asteroid.position)
+            .collect::<Vec<_>>();
+        for position in destroyed {
+            self.spawn_explosion(position);
+            self.sounds
+                .push(AudioStart::at("asteroid_explosion", position));
+        }
+        self.asteroids.retain(|asteroid| asteroid.health > 0.0);
+    }
+
+    fn collide_ships(&mut self) {
+        for player in 0..PLAYERS {
+            let mut index = 0;
+            while index < self.asteroids.len() {
+                let ship = &mut self.ships[player];
+                if !ship.alive || ship.hyperspace_frames > 0 {
+                    break;
+                }
+                let asteroid = &mut self.asteroids[index];
+                let Some(impact_speed) = bounce(
+                    (&mut ship.position, &mut ship.velocity, ship_mass()),
+                    (
+                        &mut asteroid.position,
+                        &mut asteroid.velocity,
+                        asteroid_mass(asteroid.size),
+                    ),
+                    SHIP_RADIUS + ASTEROID_RADIUS * asteroid.size,
+                ) else {
+                    index += 1;
+                    continue;
+                };
+                if !self.absorb_hit(player, ship_impact_damage(impact_speed)) {
+                    self.destroy_ship(player);
+                    let asteroid = self.asteroids.remove(index);
+                    self.spawn_explosion(asteroid.position);
+                    continue;
+                }
+                index += 1;
+            }
+        }
+    }
+
+    fn collide_lasers(&mut self) {
+        let mut index = 0;
+        'lasers: while index < self.lasers.len() {
+            let laser = self.lasers[index].clone();
+
+            for player in 0..PLAYERS {
+                let ship = &self.ships[player];
+                if player == laser.fired_by
+                    || !ship.alive
+                    || ship.hyperspace_frames > 0
+                    || ship.position.distance_squared(laser.position) > SHIP_RADIUS * SHIP_RADIUS
+                {
+                    continue;
+                }
+                self.lasers.remove(index);
+                if !self.absorb_hit(player, LASER_SHIP_DAMAGE) {
+                    self.destroy_ship(player);
+                    self.ships[laser.fired_by].score += SHIP_KILL_SCORE;
+                }
+                continue 'lasers;
+            }
+
+            if let Some(hit) = self.asteroids.iter().position(|asteroid| {
+                let radius = ASTEROID_RADIUS * asteroid.size;
+                asteroid.position.distance_squared(laser.position) <= radius * radius
+            }) {
+                self.lasers.remove(index);
+                let asteroid = &mut self.asteroids[hit];
+                asteroid.health -= 1.0;
+                if asteroid.health <= 0.0 {
+                    self.ships[laser.fired_by].score += asteroid_score(asteroid.max_health);
+                    let position = asteroid.position;
+                    self.asteroids.remove(hit);
+                    self.spawn_explosion(position);
+                    self.sounds
+                        .push(AudioStart::at("asteroid_explosion", position));
+                }
+                continue;
+            }
+
+            index += 1;
+        }
+    }
+
+    fn nearest_asteroid(&self, position: Vec2) -> Option<usize> {
+        (0..self.asteroids.len()).reduce(|a, b| {
+            if self.asteroids[a].position.distance_squared(position)
+                < self.asteroids[b].position.distance_squared(position)
+            {
+                a
+            } else {
+                b
+            }
+        })
+    }
+
+    fn pull_nearest_asteroids(&mut self) {
+        let positions = self
+            .ships
+            .iter()
+            .filter(|ship| ship.alive)
+            .map(|ship| ship.position)
+            .collect::<Vec<_>>();
+        for position in &positions {
+            let Some(nearest) = self.nearest_asteroid(*position) else {
+                return;
+            };
+            let asteroid = &mut self.asteroids[nearest];
+            if positions
+                .iter()
+                .all(|other| other.distance(asteroid.position) > NEAREST_PULL_DISTANCE)
+            {
+                asteroid.velocity =
+                    (*position - asteroid.position).normalize() * NEAREST_PULL_SPEED;
+            }
+        }
+    }
+
+    // Angle and distance from a ship to its nearest asteroid, for the assists.
+    // Assists only change the inputs that are sent, so they are never part of
+    // the simulation itself.
+    pub fn aim(&self, player: usize) -> Option<(f32, f32)> {
+        let ship = &self.ships[player];
+        if !ship.alive || ship.hyperspace_frames > 0 {
+            return None;
+        }
+        let nearest = self.nearest_asteroid(ship.position)?;
+        Some(assists::aim(
+            ship.position,
+            ship.heading,
+            self.asteroids[nearest].position,
+        ))
+    }
+
+    pub fn winner(&self) -> Option<usize> {
+        match (self.ships[0].eliminated, self.ships[1].eliminated) {
+            (true, false) => Some(1),
+            (false, true) => Some(0),
+            _ => None,
+        }
+    }
+
+    pub fn checksum(&self) -> u64 {
+        let mut hash = Fnv::default();
+        hash.u64(self.frame as u64)
+            .u64(self.rng)
+            .u64(self.next_id)
+            .u64(self.ammo.map_or(u64::MAX, u64::from));
+        for ship in &self.ships {
+            hash.u64(ship.id)
+                .u64(ship.alive as u64)
+                .vec2(ship.position)
+                .vec2(ship.velocity)
+                .vec2(ship.heading)
+                .f32(ship.angular_velocity)
+                .f32(ship.shield.map_or(0.0, |shield| shield.current))
+                .u64(ship.lives as u64)
+                .u64(ship.score as u64)
+                .u64(ship.eliminated as u64)
+                .u64(ship.respawn_frames as u64)
+                .u64(ship.invulnerable_frames as u64)
+                .u64(ship.fire_cooldown as u64)
+                .u64(ship.hyperspace_frames as u64)
+                .u64(ship.hyperspace_cooldown as u64)
+                .u64(ship.previous_input.0 as u64);
+        }
+        for asteroid in &self.asteroids {
+            hash.u64(asteroid.id)
+                .vec2(asteroid.position)
+                .vec2(asteroid.velocity)
+                .f32(asteroid.size)
+                .f32(asteroid.health);
+        }
+        for laser in &self.lasers {
+            hash.u64(laser.id)
+                .vec2(laser.position)
+                .vec2(laser.velocity)
+                .u64(laser.frames as u64)
+                .u64(laser.fired_by as u64);
+        }
+        for explosion in &self.explosions {
+            hash.u64(explosion.id)
+                .vec2(explosion.position)
+                .u64(explosion.frames as u64);
+        }
+        hash.0
+    }
+
+    pub fn pilots(&self) -> Vec<(u32, u32)> {
+        self.ships
+            .iter()
+            .map(|ship| (ship.lives, ship.score))
+            .collect()
+    }
+
+    pub fn entities(&self) -> Vec<NetEntity> {
+        let ships = self
+            .ships
+            .iter()
+            .enumerate()
+            .filter(|(_, ship)| ship.alive)
+            .map(|(player, ship)| NetEntity {
+                id: ship.id,
+                kind: NetKind::Ship(player as u8),
+                position: ship.position,
+                rotation: ship.heading.to_angle() - std::f32::consts::FRAC_PI_2,
+                scale: match ship.hyperspace_frames {
+                    0 => 1.0,
+                    frames => hyperspace_scale(1.0 - frames as f32 / HYPERSPACE_FRAMES as f32),
+                },
+            });
+        let asteroids = self.asteroids.iter().map(|asteroid| NetEntity {
+            id: asteroid.id,
+            kind: NetKind::Asteroid,
+            position: asteroid.position,
+            rotation: 0.0,
+            scale: asteroid.size,
+        });
+        let lasers = self.lasers.iter().map(|laser| NetEntity {
+            id: laser.id,
+            kind: NetKind::Laser,
+            position: laser.position,
+            rotation: laser.velocity.to_angle() - std::f32::consts::FRAC_PI_2,
+            scale: 0.5,
+        });
+        let explosions = self.explosions.iter().map(|explosion| NetEntity {
+            id: explosion.id,
+            kind: NetKind::Explosion,
+            position: explosion.position,
+            rotation: 0.0,
+            scale: 0.2,
+        });
+        ships
+            .chain(asteroids)
+            .chain(lasers)
+            .chain(explosions)
+            .collect()
+    }
+}
+
+// Relative masses, from avian's default density of 1 for ships
+fn ship_mass() -> f32 {
+    SHIP_RADIUS * SHIP_RADIUS
+}
+
+fn asteroid_mass(size: f32) -> f32 {
+    let radius = ASTEROID_RADIUS * size;
+    radius * radius * ASTEROID_DENSITY
+}
+
+fn rotate(heading: Vec2, angle: f32) -> Vec2 {
+    // Taylor expansion, renormalized, instead of platform dependent sin/cos
+    let angle_squared = angle * angle;
+    let cos = 1.0 - angle_squared / 2.0;
+    let sin = angle - angle * angle_squared / 6.0;
+    let rotated = Vec2::new(
+        heading.x * cos - heading.y * sin,
+        heading.x * sin + heading.y * cos,
+    );
+    rotated / rotated.length()
+}
+
+fn bounce(
+    (a_position, a_velocity, a_mass): (&mut Vec2, &mut Vec2, f32),
+    (b_position, b_velocity, b_mass): (&mut Vec2, &mut Vec2, f32),
+    distance: f32,
+) -> Option<f32> {
+    let offset = *b_position - *a_position;
+    let distance_squared = offset.length_squared();
+    if distance_squared >= distance * distance || distance_squared == 0.0 {
+        return None;
+    }
+    let current = distance_squared.sqrt();
+    let normal = offset / current;
+    let total_mass = a_mass + b_mass;
+
+    let overlap = distance - current;
+    *a_position -= normal * overlap * b_mass / total_mass;
+    *b_position += normal * overlap * a_mass / total_mass;
+
+    let approach_speed = (*a_velocity - *b_velocity).dot(normal);
+    if approach_speed <= 0.0 {
+        return None;
+    }
+    *a_velocity -= normal * approach_speed * 2.0 * b_mass / total_mass;
+    *b_velocity += normal * approach_speed * 2.0 * a_mass / total_mass;
+    Some(approach_speed)
+}
+
+struct Fnv(u64);
+
+impl Default for Fnv {
+    fn default() -> Self {
+        Fnv(0xcbf29ce484222325)
+    }
+}
+
+impl Fnv {
+    fn u64(&mut self, value: u64) -> &mut Self {
+        for byte in value.to_le_bytes() {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+        self
+    }
+
+    fn f32(&mut self, value: f32) -> &mut Self {
+        self.u64(value.to_bits() as u64)
+    }
+
+    fn vec2(&mut self, value: Vec2) -> &mut Self {
+        self.f32(value.x).f32(value.y)
+    }
+}
@@ -0,0 +1,234 @@
+use bevy::{ecs::system::SystemParam, prelude::*, time::Stopwatch};
+
+use crate::{
+    GameMode, GameState,
+    audio::AudioStart,
+    game::{Ammo, Asteroid, Laser, Pilots},
+    level::CurrentLevel,
+    localization::Locale,
+};
+
+pub fn objectives_plugin(app: &mut App) {
+    app.add_systems(OnEnter(GameState::Game), start_timer)
+        .add_systems(
+            Update,
+            (
+                tick_timer,
+                evaluate_objectives.run_if(|game_mode: Res<GameMode>| game_mode.has_objectives()),
+            )
+                .chain()
+                .run_if(in_state(GameState::Game)),
+        );
+}
+
+#[derive(Resource)]
+pub struct GameDuration(pub Stopwatch);
+
+pub struct Progress {
+    pub asteroids: usize,
+    pub elapsed: f32,
+    pub score: u32,
+    pub ammo: Option<u32>,
+    pub lasers: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectiveStatus {
+    InProgress,
+    Completed,
+    Failed,
+}
+
+pub trait Objective: Send + Sync {
+    fn status(&self, progress: &Progress) -> ObjectiveStatus;
+
+    fn describe(&self, progress: &Progress, locale: &Locale) -> String;
+
+    /// Constraints only hold while the level is played and can't win it on their own
+    fn is_goal(&self) -> bool {
+        true
+    }
+
+    /// Seconds left before the objective runs out of time, if it is timed
+    fn time_left(&self, _progress: &Progress) -> Option<f32> {
+        None
+    }
+}
+
+pub struct ClearAsteroids;
+
+impl Objective for ClearAsteroids {
+    fn status(&self, progress: &Progress) -> ObjectiveStatus {
+        if progress.asteroids == 0 {
+            ObjectiveStatus::Completed
+        } else {
+            ObjectiveStatus::InProgress
+        }
+    }
+
+    fn describe(&self, progress: &Progress, locale: &Locale) -> String {
+        locale.format(
+            "objective-clear",
+            &[("count", progress.asteroids.to_string())],
+        )
+    }
+}
+
+pub struct TimeLimit(pub f32);
+
+impl Objective for TimeLimit {
+    fn status(&self, progress: &Progress) -> ObjectiveStatus {
+        if progress.elapsed < self.0 {
+            ObjectiveStatus::Completed
+        } else {
+            ObjectiveStatus::Failed
+        }
+    }
+
+    fn describe(&self, progress: &Progress, locale: &Locale) -> String {
+        locale.format(
+            "objective-time",
+            &[(
+                "seconds",
+                format!("{:.0}", (self.0 - progress.elapsed).max(0.0)),
+            )],
+        )
+    }
+
+    fn is_goal(&self) -> bool {
+        false
+    }
+
+    fn time_left(&self, progress: &Progress) -> Option<f32> {
+        Some((self.0 - progress.elapsed).max(0.0))
+    }
+}
+
+pub struct TargetScore(pub u32);
+
+impl Objective for TargetScore {
+    fn status(&self, progress: &Progress) -> ObjectiveStatus {
+        if progress.score >= self.0 {
+            ObjectiveStatus::Completed
+        } else {
+            ObjectiveStatus::InProgress
+        }
+    }
+
+    fn describe(&self, progress: &Progress, locale: &Locale) -> String {
+        locale.format(
+            "objective-score",
+            &[
+                ("score", progress.score.min(self.0).to_string()),
+                ("target", self.0.to_string()),
+            ],
+        )
+    }
+}
+
+pub struct Survive(pub f32);
+
+impl Objective for Survive {
+    fn status(&self, progress: &Progress) -> ObjectiveStatus {
+        if progress.elapsed >= self.0 {
+            ObjectiveStatus::Completed
+        } else {
+            ObjectiveStatus::InProgress
+        }
+    }
+
+    fn describe(&self, progress: &Progress, locale: &Locale) -> String {
+        locale.format(
+            "objective-survive",
+            &[
+                ("elapsed", format!("{:.0}", progress.elapsed.min(self.0))),
+                ("duration", format!("{:.0}", self.0)),
+            ],
+        )
+    }
+}
+
+pub struct LimitedAmmo;
+
+impl Objective for LimitedAmmo {
+    fn status(&self, progress: &Progress) -> ObjectiveStatus {
+        if progress.ammo != Some(0) || progress.lasers > 0 {
+            ObjectiveStatus::Completed
+        } else {
+            ObjectiveStatus::Failed
+        }
+    }
+
+    fn describe(&self, progress: &Progress, locale: &Locale) -> String {
+        locale.format(
+            "objective-ammo",
+            &[("ammo", progress.ammo.unwrap_or_default().to_string())],
+        )
+    }
+
+    fn is_goal(&self) -> bool {
+        false
+    }
+}
+
+#[derive(SystemParam)]
+pub struct ObjectiveProgress<'w, 's> {
+    pub level: CurrentLevel<'w>,
+    asteroids: Query<'w, 's, (), With<Asteroid>>,
+    lasers: Query<'w, 's, (), With<Laser>>,
+    duration: Res<'w, GameDuration>,
+    pilots: Res<'w, Pilots>,
+    ammo: Option<Res<'w, Ammo>>,
+}
+
+impl ObjectiveProgress<'_, '_> {
+    pub fn progress(&self) -> Progress {
+        Progress {
+            asteroids: self.asteroids.iter().len(),
+            elapsed: self.duration.0.elapsed_secs(),
+            score: self.pilots.total_score(),
+            ammo: self.ammo.as_ref().map(|ammo| ammo.0),
+            lasers: self.lasers.iter().len(),
+        }
+    }
+}
+
+fn start_timer(mut commands: Commands) {
+    commands.insert_resource(GameDuration(Stopwatch::new()));
+}
+
+fn tick_timer(time: Res<Time>, mut duration: ResMut<GameDuration>) {
+    duration.0.tick(time.delta());
+}
+
+fn evaluate_objectives(
+    objectives: ObjectiveProgress,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut audio: EventWriter<AudioStart>,
+) {
+    let progress = objectives.progress();
+    let statuses = objectives
+        .level
+        .get()
+        .objectives
+        .iter()
+        .map(|objective| (objective.is_goal(), objective.status(&progress)))
+        .collect::<Vec<_>>();
+
+    // Meeting the goals wins even if a constraint fails on the same frame, like
+    // running out of ammo with the shot that destroys the last asteroid
+    if statuses
+        .iter()
+        .filter(|(is_goal, _)| *is_goal)
+        .all(|(_, status)| *status == ObjectiveStatus::Completed)
+    {
+        next_state.set(GameState::Won);
+        audio.write(AudioStart::new("win"));
+    } else if statuses
+        .iter()
+        .any(|(_, status)| *status == ObjectiveStatus::Failed)
+    {
+        next_state.set(GameState::StartMenu);
+        audio.write(AudioStart::new("lose"));
+    }
+}
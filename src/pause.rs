@@ -0,0 +1,124 @@
+use bevy::prelude::*;
+use bevy_enhanced_input::prelude::*;
+
+use crate::{GameState, localization::Localized, settings_menu::SettingsMenu, theme::Themed};
+
+pub fn pause_plugin(app: &mut App) {
+    app.add_sub_state::<Pause>()
+        .enable_state_scoped_entities::<Pause>()
+        .add_systems(OnEnter(Pause::Paused), (pause, display_pause_menu))
+        .add_systems(OnExit(Pause::Paused), resume)
+        .add_systems(
+            Update,
+            (
+                toggle_pause.run_if(in_state(GameState::Game)),
+                pause_menu.run_if(in_state(Pause::Paused)),
+            )
+                .run_if(in_state(SettingsMenu::Closed)),
+        );
+}
+
+#[derive(SubStates, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[source(GameState = GameState::Game)]
+pub(crate) enum Pause {
+    #[default]
+    Running,
+    Paused,
+}
+
+fn pause(mut time: ResMut<Time<Virtual>>, mut sources: ResMut<ActionSources>) {
+    time.pause();
+    // Ships and camera ignore the keyboard while the menu is open
+    *sources = ActionSources {
+        keyboard: false,
+        mouse_buttons: false,
+        mouse_motion: false,
+        mouse_wheel: false,
+        gamepad_button: false,
+        gamepad_axis: false,
+    };
+}
+
+fn resume(mut time: ResMut<Time<Virtual>>, mut sources: ResMut<ActionSources>) {
+    time.unpause();
+    *sources = ActionSources::default();
+}
+
+fn display_pause_menu(mut commands: Commands) {
+    commands.spawn((
+        Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            flex_direction: FlexDirection::Column,
+            ..default()
+        },
+        BackgroundColor(Color::BLACK.with_alpha(0.6)),
+        GlobalZIndex(1),
+        children![
+            (
+                Text::default(),
+                Localized::new("pause-title"),
+                TextFont {
+                    font_size: 150.0,
+                    ..default()
+                },
+                Themed::Title,
+            ),
+            (
+                Text::default(),
+                Localized::new("pause-resume"),
+                TextFont {
+                    font_size: 50.0,
+                    ..default()
+                },
+                Themed::Text,
+            ),
+            (
+                Text::default(),
+                Localized::new("pause-settings"),
+                TextFont {
+                    font_size: 40.0,
+                    ..default()
+                },
+                Themed::Text,
+            ),
+            (
+                Text::default(),
+                Localized::new("pause-quit"),
+                TextFont {
+                    font_size: 40.0,
+                    ..default()
+                },
+                Themed::Text,
+            )
+        ],
+        StateScoped(Pause::Paused),
+    ));
+}
+
+fn toggle_pause(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    pause: Res<State<Pause>>,
+    mut next: ResMut<NextState<Pause>>,
+) {
+    if keyboard.just_pressed(KeyCode::Escape) {
+        next.set(match pause.get() {
+            Pause::Running => Pause::Paused,
+            Pause::Paused => Pause::Running,
+        });
+    }
+}
+
+fn pause_menu(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut next_menu: ResMut<NextState<SettingsMenu>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if keyboard.just_pressed(KeyCode::KeyS) {
+        next_menu.set(SettingsMenu::Open);
+    } else if keyboard.just_pressed(KeyCode::KeyQ) {
+        next_state.set(GameState::StartMenu);
+    }
+}
@@ -0,0 +1,480 @@
+use std::{fmt, str::FromStr};
+
+use bevy::{
+    prelude::*,
+    window::{PresentMode, PrimaryWindow, WindowMode},
+};
+use thiserror::Error;
+
+use crate::{
+    assists::Assists,
+    audio::Mixer,
+    game::{Controls, DEFAULT_CONTROLS},
+    graphics::GraphicsSettings,
+    localization::Language,
+    theme::Palette,
+};
+
+pub fn settings_plugin(app: &mut App) {
+    app.insert_resource(Settings::load()).add_systems(
+        Update,
+        (
+            apply_display.run_if(resource_changed::<Settings>),
+            save_settings.run_if(resource_changed::<Settings>.and(not(resource_added::<Settings>))),
+        ),
+    );
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct Settings {
+    pub fullscreen: bool,
+    pub vsync: bool,
+    pub graphics: GraphicsSettings,
+    pub mixer: Mixer,
+    pub controls: [Controls; 2],
+    pub reduced_motion: bool,
+    pub palette: Palette,
+    pub language: Language,
+    pub assists: Assists,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            fullscreen: false,
+            vsync: true,
+            graphics: GraphicsSettings::default(),
+            mixer: Mixer::default(),
+            controls: DEFAULT_CONTROLS,
+            reduced_motion: false,
+            palette: Palette::Default,
+            language: Language::English,
+            assists: Assists::default(),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum SettingsError {
+    #[error("Could not access settings: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Error in settings format")]
+    FormatError,
+    #[error("No place to store settings")]
+    NoStorage,
+}
+
+// Limits of the values the settings menu can set, other values are rejected
+pub const MAX_BLOOM_THRESHOLD: f32 = 2.0;
+pub const MIN_PARTICLE_DENSITY: f32 = 0.25;
+pub const MIN_GAME_SPEED: f32 = 0.5;
+
+fn parse<T: FromStr>(word: Option<&str>) -> Result<T, SettingsError> {
+    word.and_then(|s| s.parse().ok())
+        .ok_or(SettingsError::FormatError)
+}
+
+fn parse_in(word: Option<&str>, min: f32, max: f32) -> Result<f32, SettingsError> {
+    let value = parse(word)?;
+    if (min..=max).contains(&value) {
+        Ok(value)
+    } else {
+        Err(SettingsError::FormatError)
+    }
+}
+
+// Settings are stored one per line, as a key followed by its values, like levels
+impl FromStr for Settings {
+    type Err = SettingsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut settings = Settings::default();
+
+        // A bad line only leaves its own setting at the default, so settings
+        // from other versions don't throw away the rest of the file
+        for line in s.lines().filter(|line| !line.trim().is_empty()) {
+            let mut parsed = settings.clone();
+            match parsed.parse_line(line) {
+                Ok(()) => settings = parsed,
+                Err(_) => warn!("Ignoring invalid setting: {line}"),
+            }
+        }
+        // Checked once the whole file is read, as keys can be swapped line by line
+        if has_duplicate_keys(&settings.controls) {
+            warn!("Ignoring key bindings that use a key twice");
+            settings.controls = DEFAULT_CONTROLS;
+        }
+
+        Ok(settings)
+    }
+}
+
+impl Settings {
+    fn parse_line(&mut self, line: &str) -> Result<(), SettingsError> {
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("fullscreen") => self.fullscreen = parse(words.next())?,
+            Some("vsync") => self.vsync = parse(words.next())?,
+            Some("bloom") => {
+                self.graphics.bloom = parse(words.next())?;
+                self.graphics.bloom_intensity = parse_in(words.next(), 0.0, 1.0)?;
+                self.graphics.bloom_threshold = parse_in(words.next(), 0.0, MAX_BLOOM_THRESHOLD)?;
+            }
+            Some("hdr") => self.graphics.hdr = parse(words.next())?,
+            Some("particles") => {
+                self.graphics.particle_density = parse_in(words.next(), MIN_PARTICLE_DENSITY, 1.0)?;
+            }
+            Some("starfield") => self.graphics.starfield = parse(words.next())?,
+            Some("volume") => {
+                let name = words.next();
+                let (_, bus) = self
+                    .mixer
+                    .buses_mut()
+                    .into_iter()
+                    .find(|(bus, _)| Some(*bus) == name)
+                    .ok_or(SettingsError::FormatError)?;
+                bus.volume = parse_in(words.next(), 0.0, 1.0)?;
+                bus.muted = parse(words.next())?;
+            }
+            Some("key") => {
+                let controls = self
+                    .controls
+                    .get_mut(parse::<usize>(words.next())?.wrapping_sub(1))
+                    .ok_or(SettingsError::FormatError)?;
+                let action = words.next();
+                let (_, key) = controls
+                    .bindings_mut()
+                    .into_iter()
+                    .find(|(name, _)| Some(*name) == action)
+                    .ok_or(SettingsError::FormatError)?;
+                *key = parse_key(words.next()).ok_or(SettingsError::FormatError)?;
+            }
+            Some("reduced_motion") => self.reduced_motion = parse(words.next())?,
+            Some("palette") => self.palette = parse(words.next())?,
+            Some("language") => self.language = parse(words.next())?,
+            Some("game_speed") => {
+                self.assists.game_speed = parse_in(words.next(), MIN_GAME_SPEED, 1.0)?;
+            }
+            Some("toggle_thrust") => self.assists.toggle_thrust = parse(words.next())?,
+            Some("auto_fire") => self.assists.auto_fire = parse(words.next())?,
+            Some("aim_assist") => self.assists.aim_assist = parse(words.next())?,
+            Some("infinite_lives") => self.assists.infinite_lives = parse(words.next())?,
+            _ => return Err(SettingsError::FormatError),
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Settings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "fullscreen {}", self.fullscreen)?;
+        writeln!(f, "vsync {}", self.vsync)?;
+        let graphics = &self.graphics;
+        writeln!(
+            f,
+            "bloom {} {} {}",
+            graphics.bloom, graphics.bloom_intensity, graphics.bloom_threshold
+        )?;
+        writeln!(f, "hdr {}", graphics.hdr)?;
+        writeln!(f, "particles {}", graphics.particle_density)?;
+        writeln!(f, "starfield {}", graphics.starfield)?;
+        for (name, bus) in self.mixer.buses() {
+            writeln!(f, "volume {name} {} {}", bus.volume, bus.muted)?;
+        }
+        for (player, controls) in self.controls.iter().enumerate() {
+            for (action, key) in controls.bindings() {
+                writeln!(f, "key {} {action} {}", player + 1, key_name(key))?;
+            }
+        }
+        writeln!(f, "reduced_motion {}", self.reduced_motion)?;
+        writeln!(f, "palette {}", self.palette)?;
+        writeln!(f, "language {}", self.language)?;
+        let assists = &self.assists;
+        writeln!(f, "game_speed {}", assists.game_speed)?;
+        writeln!(f, "toggle_thrust {}", assists.toggle_thrust)?;
+        writeln!(f, "auto_fire {}", assists.auto_fire)?;
+        writeln!(f, "aim_assist {}", assists.aim_assist)?;
+        writeln!(f, "infinite_lives {}", assists.infinite_lives)
+    }
+}
+
+impl Settings {
+    fn load() -> Self {
+        match read_settings().and_then(|settings| settings.parse()) {
+            Ok(settings) => settings,
+            Err(SettingsError::Io(error)) if error.kind() == std::io::ErrorKind::NotFound => {
+                Settings::default()
+            }
+            Err(error) => {
+                warn!("Using default settings: {error}");
+                Settings::default()
+            }
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn settings_path() -> Result<std::path::PathBuf, SettingsError> {
+    let config = std::env::var_os("XDG_CONFIG_HOME")
+        .or_else(|| std::env::var_os("APPDATA"))
+        .map(std::path::PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| std::path::Path::new(&home).join(".config"))
+        })
+        .ok_or(SettingsError::NoStorage)?;
+    Ok(config.join("bevy_workshop").join("settings.cfg"))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn read_settings() -> Result<String, SettingsError> {
+    Ok(std::fs::read_to_string(settings_path()?)?)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write_settings(settings: &str) -> Result<(), SettingsError> {
+    let path = settings_path()?;
+    if let Some(directory) = path.parent() {
+        std::fs::create_dir_all(directory)?;
+    }
+    Ok(std::fs::write(path, settings)?)
+}
+
+// On the web, settings are kept in the browser's local storage
+#[cfg(target_arch = "wasm32")]
+const STORAGE_KEY: &str = "bevy_workshop.settings";
+
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Result<web_sys::Storage, SettingsError> {
+    web_sys::window()
+        .and_then(|window| window.local_storage().ok().flatten())
+        .ok_or(SettingsError::NoStorage)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn read_settings() -> Result<String, SettingsError> {
+    local_storage()?
+        .get_item(STORAGE_KEY)
+        .ok()
+        .flatten()
+        .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::NotFound).into())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn write_settings(settings: &str) -> Result<(), SettingsError> {
+    local_storage()?
+        .set_item(STORAGE_KEY, settings)
+        .map_err(|_| SettingsError::NoStorage)
+}
+
+fn save_settings(settings: Res<Settings>) {
+    if let Err(error) = write_settings(&settings.to_string()) {
+        warn!("Could not save settings: {error}");
+    }
+}
+
+fn apply_display(settings: Res<Settings>, mut windows: Query<&mut Window, With<PrimaryWindow>>) {
+    let Ok(mut window) = windows.single_mut() else {
+        return;
+    };
+    window.mode = if settings.fullscreen {
+        WindowMode::BorderlessFullscreen(MonitorSelection::Current)
+    } else {
+        WindowMode::Windowed
+    };
+    window.present_mode = if settings.vsync {
+        PresentMode::AutoVsync
+    } else {
+        PresentMode::AutoNoVsync
+    };
+}
+
+// Keys that can be bound, with the names used in the settings file and menus
+const KEY_NAMES: [(KeyCode, &str); 68] = [
+    (KeyCode::KeyA, "A"),
+    (KeyCode::KeyB, "B"),
+    (KeyCode::KeyC, "C"),
+    (KeyCode::KeyD, "D"),
+    (KeyCode::KeyE, "E"),
+    (KeyCode::KeyF, "F"),
+    (KeyCode::KeyG, "G"),
+    (KeyCode::KeyH, "H"),
+    (KeyCode::KeyI, "I"),
+    (KeyCode::KeyJ, "J"),
+    (KeyCode::KeyK, "K"),
+    (KeyCode::KeyL, "L"),
+    (KeyCode::KeyM, "M"),
+    (KeyCode::KeyN, "N"),
+    (KeyCode::KeyO, "O"),
+    (KeyCode::KeyP, "P"),
+    (KeyCode::KeyQ, "Q"),
+    (KeyCode::KeyR, "R"),
+    (KeyCode::KeyS, "S"),
+    (KeyCode::KeyT, "T"),
+    (KeyCode::KeyU, "U"),
+    (KeyCode::KeyV, "V"),
+    (KeyCode::KeyW, "W"),
+    (KeyCode::KeyX, "X"),
+    (KeyCode::KeyY, "Y"),
+    (KeyCode::KeyZ, "Z"),
+    (KeyCode::Digit0, "0"),
+    (KeyCode::Digit1, "1"),
+    (KeyCode::Digit2, "2"),
+    (KeyCode::Digit3, "3"),
+    (KeyCode::Digit4, "4"),
+    (KeyCode::Digit5, "5"),
+    (KeyCode::Digit6, "6"),
+    (KeyCode::Digit7, "7"),
+    (KeyCode::Digit8, "8"),
+    (KeyCode::Digit9, "9"),
+    (KeyCode::ArrowUp, "Up"),
+    (KeyCode::ArrowDown, "Down"),
+    (KeyCode::ArrowLeft, "Left"),
+    (KeyCode::ArrowRight, "Right"),
+    (KeyCode::Space, "Space"),
+    (KeyCode::Enter, "Enter"),
+    (KeyCode::Tab, "Tab"),
+    (KeyCode::Backspace, "Backspace"),
+    (KeyCode::ShiftLeft, "LeftShift"),
+    (KeyCode::ShiftRight, "RightShift"),
+    (KeyCode::ControlLeft, "LeftCtrl"),
+    (KeyCode::ControlRight, "RightCtrl"),
+    (KeyCode::AltLeft, "LeftAlt"),
+    (KeyCode::AltRight, "RightAlt"),
+    (KeyCode::Comma, "Comma"),
+    (KeyCode::Period, "Period"),
+    (KeyCode::Slash, "Slash"),
+    (KeyCode::Semicolon, "Semicolon"),
+    (KeyCode::Quote, "Quote"),
+    (KeyCode::BracketLeft, "LeftBracket"),
+    (KeyCode::BracketRight, "RightBracket"),
+    (KeyCode::Backslash, "Backslash"),
+    (KeyCode::Numpad0, "Numpad0"),
+    (KeyCode::Numpad1, "Numpad1"),
+    (KeyCode::Numpad2, "Numpad2"),
+    (KeyCode::Numpad3, "Numpad3"),
+    (KeyCode::Numpad4, "Numpad4"),
+    (KeyCode::Numpad5, "Numpad5"),
+    (KeyCode::Numpad6, "Numpad6"),
+    (KeyCode::Numpad7, "Numpad7"),
+    (KeyCode::Numpad8, "Numpad8"),
+    (KeyCode::Numpad9, "Numpad9"),
+];
+
+pub fn key_name(key: KeyCode) -> &'static str {
+    KEY_NAMES
+        .iter()
+        .find(|(code, _)| *code == key)
+        .map_or("?", |(_, name)| name)
+}
+
+fn parse_key(name: Option<&str>) -> Option<KeyCode> {
+    KEY_NAMES
+        .iter()
+        .find(|(_, key)| Some(*key) == name)
+        .map(|(code, _)| *code)
+}
+
+pub fn is_bindable(key: KeyCode) -> bool {
+    KEY_NAMES.iter().any(|(code, _)| *code == key)
+}
+
+// Both players share the keyboard, so a key can only be bound once across them
+fn has_duplicate_keys(controls: &[Controls]) -> bool {
+    let keys = controls
+        .iter()
+        .flat_map(|controls| controls.bindings().map(|(_, key)| key))
+        .collect::<Vec<_>>();
+    keys.iter()
+        .enumerate()
+        .any(|(index, key)| keys[index + 1..].contains(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bad_lines_keep_other_settings() {
+        let settings = "fullscreen true\n\
+            renamed_setting 3\n\
+            vsync maybe\n\
+            key 1 fire F\n\
+            bloom high 0.5\n\
+            language ru\n"
+            .parse::<Settings>()
+            .unwrap();
+        let defaults = Settings::default();
+
+        assert!(settings.fullscreen);
+        assert_eq!(settings.vsync, defaults.vsync);
+        assert_eq!(settings.controls[0].fire, KeyCode::KeyF);
+        assert_eq!(settings.graphics.bloom, defaults.graphics.bloom);
+        assert_eq!(
+            settings.graphics.bloom_intensity,
+            defaults.graphics.bloom_intensity
+        );
+        assert_eq!(settings.language, Language::Russian);
+    }
+
+    #[test]
+    fn out_of_range_values_keep_defaults() {
+        let settings = "game_speed -1\n\
+            volume music NaN false\n\
+            volume sfx 0.5 true\n\
+            bloom low 0.5 inf\n\
+            particles 0\n"
+            .parse::<Settings>()
+            .unwrap();
+        let defaults = Settings::default();
+
+        assert_eq!(settings.assists.game_speed, defaults.assists.game_speed);
+        for speed in ["0", "inf", "NaN", "1.5"] {
+            let settings = format!("game_speed {speed}").parse::<Settings>().unwrap();
+            assert_eq!(settings.assists.game_speed, defaults.assists.game_speed);
+        }
+        let [(_, music), (_, sfx)] = [1, 2].map(|bus| settings.mixer.buses()[bus]);
+        assert_eq!(music.volume, 1.0);
+        assert_eq!(sfx.volume, 0.5);
+        assert_eq!(
+            settings.graphics.bloom_threshold,
+            defaults.graphics.bloom_threshold
+        );
+        assert_eq!(
+            settings.graphics.particle_density,
+            defaults.graphics.particle_density
+        );
+    }
+
+    #[test]
+    fn saved_settings_load_back() {
+        let mut settings = Settings {
+            fullscreen: true,
+            ..default()
+        };
+        settings.mixer.buses_mut()[1].1.volume = 0.25;
+        settings.assists.auto_fire = true;
+
+        let loaded = settings.to_string().parse::<Settings>().unwrap();
+        assert_eq!(loaded.to_string(), settings.to_string());
+    }
+
+    #[test]
+    fn duplicate_keys_are_rejected() {
+        let swapped = "key 1 fire W
+key 1 thrust Space
+key 2 fire Numpad9
+"
+        .parse::<Settings>()
+        .unwrap();
+        assert_eq!(swapped.controls[0].fire, KeyCode::KeyW);
+        assert_eq!(swapped.controls[0].thrust, KeyCode::Space);
+        assert_eq!(swapped.controls[1].fire, KeyCode::Numpad9);
+
+        let shared = "key 1 fire W
+key 2 fire W
+"
+        .parse::<Settings>()
+        .unwrap();
+        assert_eq!(shared.controls, DEFAULT_CONTROLS);
+    }
+}
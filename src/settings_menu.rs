@@ -0,0 +1,475 @@
+use bevy::prelude::*;
+
+use crate::{
+    graphics::{BloomLevel, StarfieldQuality},
+    localization::{Locale, Localized},
+    settings::{
+        MAX_BLOOM_THRESHOLD, MIN_GAME_SPEED, MIN_PARTICLE_DENSITY, Settings, is_bindable, key_name,
+    },
+    theme::{Theme, Themed},
+};
+
+const VOLUME_STEP: f32 = 0.1;
+const BLOOM_INTENSITY_STEP: f32 = 0.05;
+const BLOOM_THRESHOLD_STEP: f32 = 0.1;
+const PARTICLE_DENSITY_STEP: f32 = 0.25;
+const GAME_SPEED_STEP: f32 = 0.1;
+
+pub fn settings_menu_plugin(app: &mut App) {
+    app.init_state::<SettingsMenu>()
+        .enable_state_scoped_entities::<SettingsMenu>()
+        .init_resource::<Cursor>()
+        .add_systems(OnEnter(SettingsMenu::Open), display_settings)
+        .add_systems(
+            Update,
+            (
+                (navigate, rebind).run_if(in_state(SettingsMenu::Open)),
+                update_rows.run_if(
+                    resource_changed::<Settings>
+                        .or(resource_changed::<Cursor>)
+                        .or(resource_changed::<Theme>),
+                ),
+            )
+                .chain(),
+        );
+}
+
+// Shown on top of whichever screen opened it
+#[derive(States, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SettingsMenu {
+    #[default]
+    Closed,
+    Open,
+}
+
+#[derive(Clone, Copy)]
+enum Row {
+    Language,
+    Fullscreen,
+    Vsync,
+    Hdr,
+    Bloom,
+    BloomIntensity,
+    BloomThreshold,
+    ParticleDensity,
+    Starfield,
+    Volume(usize),
+    Key { player: usize, action: usize },
+    ReducedMotion,
+    Palette,
+    GameSpeed,
+    ToggleThrust,
+    AutoFire,
+    AimAssist,
+    InfiniteLives,
+}
+
+// Headings and their rows, laid out in two columns to fit on screen
+fn columns() -> [Vec<(&'static str, Vec<Row>)>; 2] {
+    [
+        vec![
+            (
+                "settings-display",
+                vec![Row::Language, Row::Fullscreen, Row::Vsync],
+            ),
+            (
+                "settings-graphics",
+                vec![
+                    Row::Hdr,
+                    Row::Bloom,
+                    Row::BloomIntensity,
+                    Row::BloomThreshold,
+                    Row::ParticleDensity,
+                    Row::Starfield,
+                ],
+            ),
+            ("settings-audio", (0..4).map(Row::Volume).collect()),
+            (
+                "settings-accessibility",
+                vec![Row::ReducedMotion, Row::Palette],
+            ),
+        ],
+        vec![
+            (
+                "settings-controls",
+                (0..2)
+                    .flat_map(|player| (0..5).map(move |action| Row::Key { player, action }))
+                    .collect(),
+            ),
+            (
+                "settings-assists",
+                vec![
+                    Row::GameSpeed,
+                    Row::ToggleThrust,
+                    Row::AutoFire,
+                    Row::AimAssist,
+                    Row::InfiniteLives,
+                ],
+            ),
+        ],
+    ]
+}
+
+fn rows() -> Vec<Row> {
+    columns()
+        .into_iter()
+        .flatten()
+        .flat_map(|(_, rows)| rows)
+        .collect()
+}
+
+#[derive(Resource, Default)]
+struct Cursor {
+    row: usize,
+    // Waiting for a key to bind to the selected row
+    rebinding: bool,
+}
+
+#[derive(Component)]
+struct SettingsRow(usize);
+
+fn on_off(value: bool, locale: &Locale) -> String {
+    locale.get(if value { "on" } else { "off" })
+}
+
+impl Row {
+    fn label(&self, settings: &Settings, locale: &Locale) -> String {
+        let graphics = &settings.graphics;
+        let assists = &settings.assists;
+        let (key, value) = match *self {
+            Row::Language => (
+                "settings-language",
+                locale.get(&format!("language-{}", settings.language)),
+            ),
+            Row::Fullscreen => ("settings-fullscreen", on_off(settings.fullscreen, locale)),
+            Row::Vsync => ("settings-vsync", on_off(settings.vsync, locale)),
+            Row::Hdr => ("settings-hdr", on_off(graphics.hdr, locale)),
+            Row::Bloom => (
+                if graphics.hdr {
+                    "settings-bloom"
+                } else {
+                    "settings-bloom-needs-hdr"
+                },
+                locale.get(&format!("bloom-{}", graphics.bloom)),
+            ),
+            Row::BloomIntensity => (
+                "settings-bloom-intensity",
+                format!("{:.2}", graphics.bloom_intensity),
+            ),
+            Row::BloomThreshold => (
+                "settings-bloom-threshold",
+                format!("{:.1}", graphics.bloom_threshold),
+            ),
+            Row::ParticleDensity => (
+                "settings-particles",
+                format!("{:.0}", graphics.particle_density * 100.0),
+            ),
+            Row::Starfield => (
+                "settings-starfield",
+                locale.get(&format!("starfield-{}", graphics.starfield)),
+            ),
+            Row::Volume(bus) => {
+                let (name, bus) = settings.mixer.buses()[bus];
+                return locale.format(
+                    if bus.muted {
+                        "settings-volume-muted"
+                    } else {
+                        "settings-volume"
+                    },
+                    &[
+                        ("bus", locale.get(&format!("bus-{name}"))),
+                        ("value", format!("{:.0}", bus.volume * 100.0)),
+                    ],
+                );
+            }
+            Row::Key { player, action } => {
+                let (name, key) = settings.controls[player].bindings()[action];
+                return locale.format(
+                    "settings-key",
+                    &[
+                        ("player", (player + 1).to_string()),
+                        ("action", locale.get(&format!("action-{name}"))),
+                        ("key", key_name(key).to_string()),
+                    ],
+                );
+            }
+            Row::ReducedMotion => (
+                "settings-reduced-motion",
+                on_off(settings.reduced_motion, locale),
+            ),
+            Row::Palette => (
+                "settings-palette",
+                locale.get(&format!("palette-{}", settings.palette)),
+            ),
+            Row::GameSpeed => (
+                "settings-game-speed",
+                format!("{:.0}", assists.game_speed * 100.0),
+            ),
+            Row::ToggleThrust => (
+                "settings-toggle-thrust",
+                on_off(assists.toggle_thrust, locale),
+            ),
+            Row::AutoFire => ("settings-auto-fire", on_off(assists.auto_fire, locale)),
+            Row::AimAssist => ("settings-aim-assist", on_off(assists.aim_assist, locale)),
+            Row::InfiniteLives => (
+                "settings-infinite-lives",
+                on_off(assists.infinite_lives, locale),
+            ),
+        };
+        locale.format(key, &[("value", value)])
+    }
+
+    // Left and Right change the value by one step in their direction, Enter
+    // toggles or cycles through it with a direction of 0
+    fn change(&self, settings: &mut Settings, direction: f32) {
+        let graphics = &mut settings.graphics;
+        match *self {
+            Row::Fullscreen => settings.fullscreen = !settings.fullscreen,
+            Row::Vsync => settings.vsync = !settings.vsync,
+            Row::Hdr => graphics.hdr = !graphics.hdr,
+            Row::Bloom => {
+                graphics.bloom = match (graphics.bloom, direction < 0.0) {
+                    (BloomLevel::Off, false) | (BloomLevel::High, true) => BloomLevel::Low,
+                    (BloomLevel::Low, false) => BloomLevel::High,
+                    (BloomLevel::Low, true) | (BloomLevel::High, false) => BloomLevel::Off,
+                    (BloomLevel::Off, true) => BloomLevel::High,
+                }
+            }
+            Row::BloomIntensity => {
+                graphics.bloom_intensity =
+                    (graphics.bloom_intensity + direction * BLOOM_INTENSITY_STEP).clamp(0.0, 1.0);
+            }
+            Row::BloomThreshold => {
+                graphics.bloom_threshold = (graphics.bloom_threshold
+                    + direction * BLOOM_THRESHOLD_STEP)
+                    .clamp(0.0, MAX_BLOOM_THRESHOLD);
+            }
+            Row::ParticleDensity => {
+                graphics.particle_density = (graphics.particle_density
+                    + direction * PARTICLE_DENSITY_STEP)
+                    .clamp(MIN_PARTICLE_DENSITY, 1.0);
+            }
+            Row::Starfield => {
+                graphics.starfield = match graphics.starfield {
+                    StarfieldQuality::Low => StarfieldQuality::High,
+                    StarfieldQuality::High => StarfieldQuality::Low,
+                }
+            }
+            Row::Volume(bus) => {
+                let (_, bus) = settings.mixer.buses_mut().into_iter().nth(bus).unwrap();
+                if direction == 0.0 {
+                    bus.muted = !bus.muted;
+                } else {
+                    bus.volume = (bus.volume + direction * VOLUME_STEP).clamp(0.0, 1.0);
+                }
+            }
+            Row::Key { .. } => (),
+            Row::ReducedMotion => settings.reduced_motion = !settings.reduced_motion,
+            Row::GameSpeed => {
+                settings.assists.game_speed = (settings.assists.game_speed
+                    + direction * GAME_SPEED_STEP)
+                    .clamp(MIN_GAME_SPEED, 1.0);
+            }
+            Row::ToggleThrust => {
+                settings.assists.toggle_thrust = !settings.assists.toggle_thrust;
+            }
+            Row::AutoFire => settings.assists.auto_fire = !settings.assists.auto_fire,
+            Row::AimAssist => settings.assists.aim_assist = !settings.assists.aim_assist,
+            Row::InfiniteLives => {
+                settings.assists.infinite_lives = !settings.assists.infinite_lives;
+            }
+            Row::Palette => {
+                settings.palette = if direction < 0.0 {
+                    settings.palette.previous()
+                } else {
+                    settings.palette.next()
+                }
+            }
+            Row::Language => {
+                settings.language = if direction < 0.0 {
+                    settings.language.previous()
+                } else {
+                    settings.language.next()
+                }
+            }
+        }
+    }
+}
+
+fn display_settings(mut commands: Commands, mut cursor: ResMut<Cursor>) {
+    *cursor = Cursor::default();
+
+    let mut index = 0;
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                flex_direction: FlexDirection::Column,
+                ..default()
+            },
+            BackgroundColor(Color::BLACK.with_alpha(0.9)),
+            GlobalZIndex(2),
+            StateScoped(SettingsMenu::Open),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::default(),
+                Localized::new("settings-title"),
+                TextFont {
+                    font_size: 80.0,
+                    ..default()
+                },
+                Themed::Title,
+            ));
+            parent
+                .spawn(Node {
+                    column_gap: Val::Px(60.0),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for column in columns() {
+                        parent
+                            .spawn(Node {
+                                flex_direction: FlexDirection::Column,
+                                ..default()
+                            })
+                            .with_children(|parent| {
+                                for (heading, rows) in column {
+                                    parent.spawn((
+                                        Text::default(),
+                                        Localized::new(heading),
+                                        TextFont {
+                                            font_size: 30.0,
+                                            ..default()
+                                        },
+                                        Themed::Title,
+                                        Node {
+                                            margin: UiRect::top(Val::Px(10.0)),
+                                            ..default()
+                                        },
+                                    ));
+                                    for _ in rows {
+                                        parent.spawn((
+                                            Text::default(),
+                                            TextFont {
+                                                font_size: 20.0,
+                                                ..default()
+                                            },
+                                            TextColor::default(),
+                                            SettingsRow(index),
+                                        ));
+                                        index += 1;
+                                    }
+                                }
+                            });
+                    }
+                });
+            parent.spawn((
+                Text::default(),
+                Localized::new("settings-hint"),
+                TextFont {
+                    font_size: 20.0,
+                    ..default()
+                },
+                Themed::Detail,
+                Node {
+                    margin: UiRect::top(Val::Px(20.0)),
+                    ..default()
+                },
+            ));
+        });
+}
+
+fn update_rows(
+    mut texts: Query<(&mut Text, &mut TextColor, &SettingsRow)>,
+    settings: Res<Settings>,
+    cursor: Res<Cursor>,
+    theme: Res<Theme>,
+    locale: Locale,
+) {
+    let rows = rows();
+    for (mut text, mut color, row) in &mut texts {
+        let selected = row.0 == cursor.row;
+        text.0 = if selected && cursor.rebinding {
+            locale.get("settings-press-key")
+        } else {
+            rows[row.0].label(&settings, &locale)
+        };
+        color.0 = if selected {
+            theme.selected
+        } else {
+            theme.detail
+        };
+    }
+}
+
+fn navigate(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut cursor: ResMut<Cursor>,
+    mut settings: ResMut<Settings>,
+    mut next: ResMut<NextState<SettingsMenu>>,
+) {
+    if cursor.rebinding {
+        return;
+    }
+    let rows = rows();
+    let row = rows[cursor.row];
+
+    if keyboard.just_pressed(KeyCode::Escape) {
+        next.set(SettingsMenu::Closed);
+    } else if keyboard.just_pressed(KeyCode::ArrowUp) {
+        cursor.row = (cursor.row + rows.len() - 1) % rows.len();
+    } else if keyboard.just_pressed(KeyCode::ArrowDown) {
+        cursor.row = (cursor.row + 1) % rows.len();
+    } else if keyboard.just_pressed(KeyCode::ArrowLeft) {
+        row.change(&mut settings, -1.0);
+    } else if keyboard.just_pressed(KeyCode::ArrowRight) {
+        row.change(&mut settings, 1.0);
+    } else if keyboard.just_pressed(KeyCode::Enter) {
+        if matches!(row, Row::Key { .. }) {
+            cursor.rebinding = true;
+        } else {
+            row.change(&mut settings, 0.0);
+        }
+    }
+}
+
+fn rebind(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut cursor: ResMut<Cursor>,
+    mut settings: ResMut<Settings>,
+) {
+    // Skip the frame where Enter started the rebinding
+    if !cursor.rebinding || cursor.is_changed() {
+        return;
+    }
+    let Some(&key) = keyboard.get_just_pressed().next() else {
+        return;
+    };
+    if key != KeyCode::Escape && !is_bindable(key) {
+        return;
+    }
+
+    if let Row::Key { player, action } = rows()[cursor.row]
+        && key != KeyCode::Escape
+    {
+        let (_, binding) = settings.controls[player]
+            .bindings_mut()
+            .into_iter()
+            .nth(action)
+            .unwrap();
+        let previous = std::mem::replace(binding, key);
+        // An action already on that key takes the old one, so no key is bound twice
+        for (other, controls) in settings.controls.iter_mut().enumerate() {
+            for (index, (_, binding)) in controls.bindings_mut().into_iter().enumerate() {
+                if *binding == key && (other, index) != (player, action) {
+                    *binding = previous;
+                }
+            }
+        }
+    }
+    cursor.rebinding = false;
+}
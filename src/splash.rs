@@ -1,6 +1,6 @@
-use bevy::{core_pipeline::bloom::Bloom, prelude::*};
+use bevy::prelude::*;
 
-use crate::{AudioAssets, GameAssets, GameState, LoadedLevel};
+use crate::{AudioAssets, GameAssets, GameState, LoadedLevel, localization::Localized};
 
 pub fn splash_plugin(app: &mut App) {
     app.add_systems(OnEnter(GameState::Splash), (display_title, load_assets))
@@ -8,14 +8,7 @@ pub fn splash_plugin(app: &mut App) {
 }
 
 fn display_title(mut commands: Commands) {
-    commands.spawn((
-        Camera2d,
-        Camera {
-            hdr: true,
-            ..default()
-        },
-        Bloom::default(),
-    ));
+    commands.spawn(Camera2d);
 
     commands.spawn((
         Node {
@@ -28,14 +21,16 @@ fn display_title(mut commands: Commands) {
         },
         children![
             (
-                Text::new("Bevy Workshop"),
+                Text::default(),
+                Localized::new("splash-title"),
                 TextFont {
                     font_size: 130.0,
                     ..default()
                 },
             ),
             (
-                Text::new("Rust Week 2025"),
+                Text::default(),
+                Localized::new("splash-subtitle"),
                 TextFont {
                     font_size: 100.0,
                     ..default()
@@ -65,21 +60,26 @@ fn load_assets(mut commands: Commands, asset_server: Res<AssetServer>) {
     commands.insert_resource(GameAssets {
         player_ship: asset_server.load("playerShip1_green.png"),
         asteroid: asset_server.load("meteorBrown_big1.png"),
+        asteroid_cracks: [
+            asset_server.load("asteroid_cracks1.png"),
+            asset_server.load("asteroid_cracks2.png"),
+        ],
         jets: asset_server.load("fire07.png"),
         explosion: asset_server.load("explosion00.png"),
         laser: asset_server.load("laserRed07.png"),
         jet_particles: asset_server.load("jet.particle.ron"),
+        ship_explosion_particles: asset_server.load("ship_explosion.particle.ron"),
+        asteroid_debris_particles: asset_server.load("asteroid_debris.particle.ron"),
+        laser_hit_particles: asset_server.load("laser_hit.particle.ron"),
     });
     commands.insert_resource(LoadedLevel {
         level: asset_server.load("level.bw"),
     });
     commands.insert_resource(AudioAssets {
-        laser: asset_server.load("laser.wav"),
-        ship_explosion: asset_server.load("ship_explosion.wav"),
-        asteroid_explosion: asset_server.load("asteroid_explosion.wav"),
-        start: asset_server.load("start.wav"),
-        win: asset_server.load("win.wav"),
-        lose: asset_server.load("lose.wav"),
-        game_loop: asset_server.load("Mission Plausible.wav"),
+        sound_bank: asset_server.load("sounds.bank.ron"),
+        music: vec![
+            asset_server.load("music_calm.wav"),
+            asset_server.load("Mission Plausible.wav"),
+        ],
     });
 }
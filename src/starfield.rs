@@ -1,32 +1,103 @@
 use bevy::{
+    asset::RenderAssetUsages,
     prelude::*,
-    render::render_resource::{AsBindGroup, ShaderRef, ShaderType},
+    render::render_resource::{
+        AsBindGroup, AsBindGroupShaderType, Extent3d, ShaderRef, TextureDimension, TextureFormat,
+    },
     sprite::{Material2d, Material2dPlugin},
-    window::WindowResized,
 };
 use rand::Rng;
 
-use crate::GameState;
+use self::uniform::StarfieldUniform;
+use crate::{
+    GameState,
+    graphics::StarfieldQuality,
+    level::{CurrentLevel, StarLayer, StarfieldSettings},
+    settings::Settings,
+};
+
+// Resolution of the texture rendered by the CPU fallback
+const CPU_RESOLUTION: u32 = 160;
 
 pub fn starfield_plugin(app: &mut bevy::prelude::App) {
     app.add_plugins(Material2dPlugin::<StarfieldMaterial>::default())
-        .add_systems(OnEnter(GameState::Game), setup)
+        .insert_resource(ForceCpu(
+            std::env::args().any(|arg| arg == "--cpu-starfield"),
+        ))
+        .insert_resource(StarfieldRenderer::Shader)
+        .add_systems(
+            Update,
+            (
+                select_renderer.run_if(resource_changed::<Settings>),
+                setup.run_if(in_state(GameState::Game)),
+            )
+                .chain(),
+        )
         .add_systems(
             PostUpdate,
-            update_starfield.run_if(in_state(GameState::Game)),
+            (
+                update_starfield.run_if(resource_equals(StarfieldRenderer::Shader)),
+                update_cpu_starfield.run_if(resource_equals(StarfieldRenderer::Cpu)),
+                resize_starfield,
+            )
+                .run_if(in_state(GameState::Game)),
         );
 }
 
-#[derive(Asset, TypePath, AsBindGroup, ShaderType, Debug, Clone)]
-#[uniform(0, StarfieldMaterial)]
+// The CPU renderer draws the same stars without a custom shader, at a lower
+// resolution. It is used for the low quality setting, and can be forced for
+// platforms and tests where the material can't be used.
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StarfieldRenderer {
+    Shader,
+    Cpu,
+}
+
+#[derive(Resource)]
+struct ForceCpu(bool);
+
+#[derive(Component)]
+struct Starfield {
+    seeds: Vec2,
+    settings: StarfieldSettings,
+}
+
+#[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
+#[uniform(0, StarfieldUniform)]
 pub struct StarfieldMaterial {
     position: Vec2,
     seeds: Vec2,
+    settings: StarfieldSettings,
+}
+
+// encase's derive emits size assertions that rustc reports as never used
+#[allow(dead_code)]
+mod uniform {
+    use bevy::{prelude::*, render::render_resource::ShaderType};
+
+    #[derive(ShaderType)]
+    pub struct StarfieldUniform {
+        pub position: Vec2,
+        pub seeds: Vec2,
+        // x: density, y: size, z: brightness, w: parallax
+        pub layers: [Vec4; 3],
+        pub twinkle: f32,
+    }
 }
 
-impl<'a> From<&'a StarfieldMaterial> for StarfieldMaterial {
-    fn from(material: &'a StarfieldMaterial) -> Self {
-        material.clone()
+impl AsBindGroupShaderType<StarfieldUniform> for StarfieldMaterial {
+    fn as_bind_group_shader_type(
+        &self,
+        _images: &bevy::render::render_asset::RenderAssets<bevy::render::texture::GpuImage>,
+    ) -> StarfieldUniform {
+        StarfieldUniform {
+            position: self.position,
+            seeds: self.seeds,
+            layers: self.settings.layers.map(|layer| {
+                Vec4::new(layer.density, layer.size, layer.brightness, layer.parallax)
+            }),
+            twinkle: self.settings.twinkle,
+        }
     }
 }
 
@@ -36,34 +107,92 @@ impl Material2d for StarfieldMaterial {
     }
 }
 
+// Switching renderer replaces the starfield, a new one is set up on the next update
+fn select_renderer(
+    mut commands: Commands,
+    mut renderer: ResMut<StarfieldRenderer>,
+    starfields: Query<Entity, With<Starfield>>,
+    force_cpu: Res<ForceCpu>,
+    settings: Res<Settings>,
+) {
+    let selected = if force_cpu.0 || settings.graphics.starfield == StarfieldQuality::Low {
+        StarfieldRenderer::Cpu
+    } else {
+        StarfieldRenderer::Shader
+    };
+    if *renderer != selected {
+        *renderer = selected;
+        for starfield in &starfields {
+            commands.entity(starfield).despawn();
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn setup(
     mut commands: Commands,
+    starfields: Query<(), With<Starfield>>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StarfieldMaterial>>,
+    mut images: ResMut<Assets<Image>>,
     windows: Query<&Window>,
+    renderer: Res<StarfieldRenderer>,
+    level: CurrentLevel,
 ) {
+    if !starfields.is_empty() {
+        return;
+    }
     let window = windows.single().unwrap();
     let size = window.width().max(window.height());
+    let seeds = Vec2::new(
+        rand::thread_rng().gen_range(0.0..1000.0),
+        rand::thread_rng().gen_range(0.0..1000.0),
+    );
+    let settings = level.get().starfield;
 
-    commands.spawn((
-        Mesh2d(meshes.add(Rectangle::default())),
-        MeshMaterial2d(materials.add(StarfieldMaterial {
-            position: Vec2::ZERO,
-            seeds: Vec2::new(
-                rand::thread_rng().gen_range(0.0..1000.0),
-                rand::thread_rng().gen_range(0.0..1000.0),
-            ),
-        })),
+    let mut starfield = commands.spawn((
+        Starfield { seeds, settings },
         Transform::from_scale(Vec3::new(size, size, 1.0)),
         StateScoped(GameState::Game),
     ));
+    match *renderer {
+        StarfieldRenderer::Shader => {
+            starfield.insert((
+                Mesh2d(meshes.add(Rectangle::default())),
+                MeshMaterial2d(materials.add(StarfieldMaterial {
+                    position: Vec2::ZERO,
+                    seeds,
+                    settings,
+                })),
+            ));
+        }
+        StarfieldRenderer::Cpu => {
+            let image = Image::new_fill(
+                Extent3d {
+                    width: CPU_RESOLUTION,
+                    height: CPU_RESOLUTION,
+                    depth_or_array_layers: 1,
+                },
+                TextureDimension::D2,
+                &[0; 8],
+                // Stars are brighter than 1 for bloom, and this float format
+                // can be filtered everywhere, including WebGL2
+                TextureFormat::Rgba16Float,
+                RenderAssetUsages::default(),
+            );
+            starfield.insert(Sprite {
+                image: images.add(image),
+                custom_size: Some(Vec2::ONE),
+                ..default()
+            });
+        }
+    }
 }
 
 fn update_starfield(
     mut starfield: Query<(&mut Transform, &MeshMaterial2d<StarfieldMaterial>), Without<Camera2d>>,
     camera: Query<Ref<Transform>, With<Camera2d>>,
     mut materials: ResMut<Assets<StarfieldMaterial>>,
-    mut resized: EventReader<WindowResized>,
 ) {
     let camera_transform = camera.single().unwrap();
     if camera_transform.is_changed() {
@@ -74,11 +203,177 @@ fn update_starfield(
         let material = materials.get_mut(&material.0).unwrap();
         material.position = camera_transform.translation.xy();
     }
+}
 
-    if let Some(resized) = resized.read().last() {
-        let (mut starfield_transform, _) = starfield.single_mut().unwrap();
+fn update_cpu_starfield(
+    mut starfield: Query<(&mut Transform, &Sprite, &Starfield), Without<Camera2d>>,
+    camera: Query<Ref<Transform>, With<Camera2d>>,
+    mut images: ResMut<Assets<Image>>,
+    time: Res<Time>,
+) {
+    let camera_transform = camera.single().unwrap();
+    let (mut starfield_transform, sprite, starfield) = starfield.single_mut().unwrap();
+    // Twinkling stars change every frame, even when the camera doesn't move
+    if camera_transform.is_changed() || starfield.settings.twinkle > 0.0 {
+        starfield_transform.translation = camera_transform.translation.with_z(-2.0);
+
+        let image = images.get_mut(&sprite.image).unwrap();
+        for y in 0..CPU_RESOLUTION {
+            for x in 0..CPU_RESOLUTION {
+                let uv = (Vec2::new(x as f32, y as f32) + 0.5) / CPU_RESOLUTION as f32;
+                let brightness = star_brightness(
+                    &starfield.settings,
+                    starfield.seeds,
+                    camera_transform.translation.xy(),
+                    uv,
+                    time.elapsed_secs_wrapped(),
+                );
+                image
+                    .set_color_at(x, y, Color::linear_rgb(brightness, brightness, brightness))
+                    .unwrap();
+            }
+        }
+    }
+}
+
+fn resize_starfield(
+    mut starfield: Query<&mut Transform, (With<Starfield>, Without<Camera2d>)>,
+    camera: Query<Ref<Projection>, With<Camera2d>>,
+    windows: Query<Ref<Window>>,
+) {
+    let projection = camera.single().unwrap();
+    let window = windows.single().unwrap();
+    if projection.is_changed() || window.is_changed() {
+        let mut starfield_transform = starfield.single_mut().unwrap();
+
+        let scale = match projection.as_ref() {
+            Projection::Orthographic(orthographic) => orthographic.scale,
+            _ => 1.0,
+        };
+        starfield_transform.scale.x = window.width().max(window.height()) * scale;
+        starfield_transform.scale.y = window.width().max(window.height()) * scale;
+    }
+}
+
+// CPU port of starfield.wgsl, kept in sync with the shader
+
+fn fract(x: f32) -> f32 {
+    x - x.floor()
+}
+
+fn rand2(p: Vec2) -> Vec2 {
+    let p2 = Vec2::new(
+        p.dot(Vec2::new(12.9898, 78.233)),
+        p.dot(Vec2::new(26.65125, 83.054_54)),
+    );
+    Vec2::new(
+        fract(p2.x.sin() * 43_758.547),
+        fract(p2.y.sin() * 43_758.547),
+    )
+}
+
+fn rand(p: Vec2) -> f32 {
+    fract(p.dot(Vec2::new(54.90898, 18.233)).sin() * 4_337.545)
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+fn stars(x: Vec2, layer: &StarLayer) -> f32 {
+    let n = x * layer.density;
+    let f = n.floor();
+
+    let mut d = 1.0e10_f32;
+    for i in -1..=1 {
+        for j in -1..=1 {
+            let cell = f + Vec2::new(i as f32, j as f32);
+            let g = (n - cell - rand2(cell % layer.density) + rand(cell))
+                / (layer.density * layer.size);
+            d = d.min(g.dot(g));
+        }
+    }
+
+    layer.brightness * smoothstep(0.95, 1.0, 1.0 - d.sqrt())
+}
+
+fn star_brightness(
+    settings: &StarfieldSettings,
+    seeds: Vec2,
+    position: Vec2,
+    uv: Vec2,
+    time: f32,
+) -> f32 {
+    let coords = Vec2::new(-position.x, position.y);
+    let offsets = [Vec2::new(seeds.x, 0.0), Vec2::new(seeds.y, 0.0), seeds];
+    let intensity = rand(uv * time).clamp(1.0 - settings.twinkle, 1.0);
+
+    settings
+        .layers
+        .iter()
+        .zip(offsets)
+        .map(|(layer, offset)| stars(uv + offset - coords * layer.parallax / 1000.0, layer))
+        .sum::<f32>()
+        * intensity
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEEDS: Vec2 = Vec2::new(123.0, 456.0);
+
+    fn pixels() -> impl Iterator<Item = Vec2> {
+        (0..CPU_RESOLUTION * CPU_RESOLUTION).map(|index| {
+            let pixel = Vec2::new(
+                (index % CPU_RESOLUTION) as f32,
+                (index / CPU_RESOLUTION) as f32,
+            );
+            (pixel + 0.5) / CPU_RESOLUTION as f32
+        })
+    }
+
+    #[test]
+    fn stars_are_drawn() {
+        let settings = StarfieldSettings::default();
+        let lit = pixels()
+            .map(|uv| star_brightness(&settings, SEEDS, Vec2::ZERO, uv, 1.0))
+            .inspect(|brightness| assert!(brightness.is_finite() && *brightness >= 0.0))
+            .filter(|brightness| *brightness > 0.0)
+            .count();
+        assert!(lit > 0 && lit < (CPU_RESOLUTION * CPU_RESOLUTION / 2) as usize);
+    }
+
+    #[test]
+    fn twinkle_dims_stars_over_time() {
+        let steady = StarfieldSettings {
+            twinkle: 0.0,
+            ..default()
+        };
+        let twinkling = StarfieldSettings::default();
+        let mut dimmed = false;
+        for uv in pixels() {
+            let full = star_brightness(&steady, SEEDS, Vec2::ZERO, uv, 1.0);
+            assert_eq!(full, star_brightness(&steady, SEEDS, Vec2::ZERO, uv, 2.0));
+            for time in [1.0, 2.0] {
+                let brightness = star_brightness(&twinkling, SEEDS, Vec2::ZERO, uv, time);
+                assert!(brightness <= full && brightness >= full * (1.0 - twinkling.twinkle));
+                dimmed |= brightness < full;
+            }
+        }
+        assert!(dimmed);
+    }
 
-        starfield_transform.scale.x = resized.width.max(resized.height);
-        starfield_transform.scale.y = resized.width.max(resized.height);
+    #[test]
+    fn stars_scroll_with_the_camera() {
+        let settings = StarfieldSettings {
+            twinkle: 0.0,
+            ..default()
+        };
+        assert!(pixels().any(|uv| {
+            star_brightness(&settings, SEEDS, Vec2::ZERO, uv, 1.0)
+                != star_brightness(&settings, SEEDS, Vec2::new(300.0, 0.0), uv, 1.0)
+        }));
     }
 }
@@ -1,51 +1,142 @@
-use bevy::{color::palettes, prelude::*};
+use bevy::prelude::*;
 
-use crate::{GameState, audio::AudioStart};
+use crate::{
+    GameMode, GameState,
+    audio::AudioStart,
+    high_scores::{HighScore, HighScores},
+    localization::Localized,
+    settings_menu::SettingsMenu,
+    theme::Themed,
+};
 
 pub fn menu_plugin(app: &mut App) {
     app.add_systems(OnEnter(GameState::StartMenu), display_title)
-        .add_systems(Update, start_game.run_if(in_state(GameState::StartMenu)));
+        .add_systems(
+            Update,
+            (open_settings, start_game)
+                .run_if(in_state(GameState::StartMenu).and(in_state(SettingsMenu::Closed))),
+        );
 }
 
-fn display_title(mut commands: Commands) {
-    commands.spawn((
-        Node {
-            width: Val::Percent(100.0),
-            height: Val::Percent(100.0),
-            align_items: AlignItems::Center,
-            justify_content: JustifyContent::Center,
-            flex_direction: FlexDirection::Column,
-            ..default()
-        },
-        children![
-            (
-                Text::new("Asteroid"),
-                TextFont {
-                    font_size: 200.0,
-                    ..default()
-                },
-                TextColor::from(palettes::tailwind::RED_600),
-            ),
-            (
-                Text::new("Press any key to start"),
-                TextFont {
-                    font_size: 70.0,
-                    ..default()
-                },
-                TextColor::from(palettes::tailwind::RED_800),
-            )
-        ],
-        StateScoped(GameState::StartMenu),
-    ));
+fn display_title(mut commands: Commands, high_scores: Res<HighScores>) {
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                flex_direction: FlexDirection::Column,
+                ..default()
+            },
+            children![
+                (
+                    Text::default(),
+                    Localized::new("menu-title"),
+                    TextFont {
+                        font_size: 200.0,
+                        ..default()
+                    },
+                    Themed::Title,
+                ),
+                (
+                    Text::default(),
+                    Localized::new("menu-start"),
+                    TextFont {
+                        font_size: 70.0,
+                        ..default()
+                    },
+                    Themed::Text,
+                ),
+                (
+                    Text::default(),
+                    Localized::new("menu-endless"),
+                    TextFont {
+                        font_size: 40.0,
+                        ..default()
+                    },
+                    Themed::Text,
+                ),
+                (
+                    Text::default(),
+                    Localized::new("menu-multiplayer"),
+                    TextFont {
+                        font_size: 40.0,
+                        ..default()
+                    },
+                    Themed::Text,
+                ),
+                (
+                    Text::default(),
+                    Localized::new("menu-settings"),
+                    TextFont {
+                        font_size: 30.0,
+                        ..default()
+                    },
+                    Themed::Text,
+                )
+            ],
+            StateScoped(GameState::StartMenu),
+        ))
+        .with_children(|parent| {
+            for high_score in &high_scores.0 {
+                let mut line = parent.spawn((
+                    Text::default(),
+                    high_score_line(high_score),
+                    TextFont {
+                        font_size: 30.0,
+                        ..default()
+                    },
+                    Themed::Detail,
+                ));
+                if high_score.assisted {
+                    line.with_child((
+                        TextSpan::default(),
+                        Localized::new("high-score-assisted"),
+                        TextFont {
+                            font_size: 30.0,
+                            ..default()
+                        },
+                        Themed::Detail,
+                    ));
+                }
+            }
+        });
+}
+
+fn open_settings(keyboard: Res<ButtonInput<KeyCode>>, mut next: ResMut<NextState<SettingsMenu>>) {
+    if keyboard.just_pressed(KeyCode::KeyS) {
+        next.set(SettingsMenu::Open);
+    }
+}
+
+fn high_score_line(high_score: &HighScore) -> Localized {
+    let line = match high_score.mode {
+        GameMode::Campaign => Localized::new("high-score-campaign"),
+        GameMode::Endless => Localized::new("high-score-endless").with_arg("wave", high_score.wave),
+        GameMode::Coop => Localized::new("high-score-coop"),
+        GameMode::Versus => Localized::new("high-score-versus"),
+    };
+    line.with_arg("score", high_score.score)
 }
 
 fn start_game(
     keyboard: Res<ButtonInput<KeyCode>>,
     mut next: ResMut<NextState<GameState>>,
+    mut game_mode: ResMut<GameMode>,
     mut audio: EventWriter<AudioStart>,
 ) {
-    if keyboard.get_just_pressed().next().is_some() {
+    if keyboard.get_just_pressed().any(|key| *key != KeyCode::KeyS) {
+        *game_mode = if keyboard.just_pressed(KeyCode::KeyE) {
+            GameMode::Endless
+        } else if keyboard.just_pressed(KeyCode::KeyC) {
+            GameMode::Coop
+        } else if keyboard.just_pressed(KeyCode::KeyV) {
+            GameMode::Versus
+        } else {
+            GameMode::Campaign
+        };
         next.set(GameState::Game);
-        audio.write(AudioStart::Start);
+        audio.write(AudioStart::new("start"));
     }
 }
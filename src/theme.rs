@@ -0,0 +1,223 @@
+use std::{fmt, str::FromStr};
+
+use bevy::{color::palettes::tailwind, prelude::*};
+
+use crate::{
+    game::{Laser, Player},
+    settings::Settings,
+};
+
+pub fn theme_plugin(app: &mut App) {
+    app.init_resource::<Theme>()
+        .add_observer(color_new_text)
+        .add_systems(
+            PreUpdate,
+            (
+                update_theme.run_if(resource_changed::<Settings>),
+                (recolor_text, recolor_sprites).run_if(resource_changed::<Theme>),
+            )
+                .chain(),
+        );
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Palette {
+    #[default]
+    Default,
+    Deuteranopia,
+    Protanopia,
+    Tritanopia,
+    HighContrast,
+}
+
+impl Palette {
+    pub fn next(self) -> Self {
+        match self {
+            Palette::Default => Palette::Deuteranopia,
+            Palette::Deuteranopia => Palette::Protanopia,
+            Palette::Protanopia => Palette::Tritanopia,
+            Palette::Tritanopia => Palette::HighContrast,
+            Palette::HighContrast => Palette::Default,
+        }
+    }
+
+    pub fn previous(self) -> Self {
+        match self {
+            Palette::Default => Palette::HighContrast,
+            Palette::Deuteranopia => Palette::Default,
+            Palette::Protanopia => Palette::Deuteranopia,
+            Palette::Tritanopia => Palette::Protanopia,
+            Palette::HighContrast => Palette::Tritanopia,
+        }
+    }
+}
+
+impl fmt::Display for Palette {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Palette::Default => "default",
+            Palette::Deuteranopia => "deuteranopia",
+            Palette::Protanopia => "protanopia",
+            Palette::Tritanopia => "tritanopia",
+            Palette::HighContrast => "high_contrast",
+        })
+    }
+}
+
+impl FromStr for Palette {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "default" => Ok(Palette::Default),
+            "deuteranopia" => Ok(Palette::Deuteranopia),
+            "protanopia" => Ok(Palette::Protanopia),
+            "tritanopia" => Ok(Palette::Tritanopia),
+            "high_contrast" => Ok(Palette::HighContrast),
+            _ => Err(()),
+        }
+    }
+}
+
+// Colours used across menus, HUD and gameplay, from the palette and HDR settings
+#[derive(Resource, Clone, Debug)]
+pub struct Theme {
+    pub title: Color,
+    pub text: Color,
+    pub detail: Color,
+    pub selected: Color,
+    pub hud: Color,
+    pub pointer: Color,
+    pub shield: Color,
+    pub laser: Color,
+    pub players: [Color; 2],
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::new(Palette::Default, true)
+    }
+}
+
+impl Theme {
+    pub fn new(palette: Palette, hdr: bool) -> Self {
+        // Lasers glow with bloom in HDR, and would clamp to white without it
+        let laser = |sdr: Color, hdr_color: Color| if hdr { hdr_color } else { sdr };
+
+        match palette {
+            Palette::Default => Theme {
+                title: tailwind::RED_600.into(),
+                text: tailwind::RED_800.into(),
+                detail: tailwind::RED_300.into(),
+                selected: tailwind::YELLOW_300.into(),
+                hud: Color::WHITE,
+                pointer: Color::hsl(0.0, 1.0, 0.5),
+                shield: Color::hsl(200.0, 1.0, 0.6),
+                laser: laser(Color::srgb(1.0, 0.35, 0.35), Color::srgb(5.0, 1.0, 1.0)),
+                players: [Color::WHITE, Color::srgb(0.6, 0.6, 1.0)],
+            },
+            // Red and green are confused, keep to oranges and blues
+            Palette::Deuteranopia => Theme {
+                title: tailwind::ORANGE_500.into(),
+                text: tailwind::ORANGE_700.into(),
+                detail: tailwind::ORANGE_200.into(),
+                selected: tailwind::SKY_300.into(),
+                hud: Color::WHITE,
+                pointer: tailwind::SKY_400.into(),
+                shield: tailwind::BLUE_400.into(),
+                laser: laser(Color::srgb(1.0, 0.6, 0.1), Color::srgb(5.0, 2.5, 0.5)),
+                players: [Color::WHITE, Color::srgb(0.6, 0.7, 1.0)],
+            },
+            // Reds also look dark, so use brighter yellows
+            Palette::Protanopia => Theme {
+                title: tailwind::YELLOW_400.into(),
+                text: tailwind::YELLOW_600.into(),
+                detail: tailwind::YELLOW_100.into(),
+                selected: tailwind::SKY_300.into(),
+                hud: Color::WHITE,
+                pointer: tailwind::SKY_400.into(),
+                shield: tailwind::BLUE_400.into(),
+                laser: laser(Color::srgb(1.0, 0.9, 0.2), Color::srgb(5.0, 4.5, 1.0)),
+                players: [Color::WHITE, Color::srgb(0.6, 0.7, 1.0)],
+            },
+            // Blue and yellow are confused, keep to reds and cyans
+            Palette::Tritanopia => Theme {
+                title: tailwind::RED_500.into(),
+                text: tailwind::RED_700.into(),
+                detail: tailwind::PINK_200.into(),
+                selected: tailwind::CYAN_300.into(),
+                hud: Color::WHITE,
+                pointer: tailwind::CYAN_400.into(),
+                shield: tailwind::PINK_400.into(),
+                laser: laser(Color::srgb(1.0, 0.3, 0.4), Color::srgb(5.0, 1.0, 1.5)),
+                players: [Color::WHITE, Color::srgb(1.0, 0.6, 0.7)],
+            },
+            Palette::HighContrast => Theme {
+                title: Color::WHITE,
+                text: Color::WHITE,
+                detail: tailwind::GRAY_200.into(),
+                selected: Color::srgb(1.0, 1.0, 0.0),
+                hud: Color::WHITE,
+                pointer: Color::srgb(1.0, 1.0, 0.0),
+                shield: Color::srgb(0.0, 1.0, 1.0),
+                laser: laser(Color::srgb(1.0, 1.0, 0.0), Color::srgb(5.0, 5.0, 0.0)),
+                players: [Color::WHITE, Color::srgb(1.0, 1.0, 0.0)],
+            },
+        }
+    }
+}
+
+// Role of a text in the theme, its colour follows palette changes
+#[derive(Component, Clone, Copy, Debug)]
+#[require(TextColor)]
+pub enum Themed {
+    Title,
+    Text,
+    Detail,
+    Hud,
+}
+
+impl Themed {
+    fn color(&self, theme: &Theme) -> Color {
+        match self {
+            Themed::Title => theme.title,
+            Themed::Text => theme.text,
+            Themed::Detail => theme.detail,
+            Themed::Hud => theme.hud,
+        }
+    }
+}
+
+fn update_theme(mut theme: ResMut<Theme>, settings: Res<Settings>) {
+    *theme = Theme::new(settings.palette, settings.graphics.hdr);
+}
+
+fn color_new_text(
+    trigger: Trigger<OnAdd, Themed>,
+    mut texts: Query<(&Themed, &mut TextColor)>,
+    theme: Res<Theme>,
+) -> Result {
+    let (themed, mut color) = texts.get_mut(trigger.target())?;
+    color.0 = themed.color(&theme);
+    Ok(())
+}
+
+fn recolor_text(mut texts: Query<(&Themed, &mut TextColor)>, theme: Res<Theme>) {
+    for (themed, mut color) in &mut texts {
+        color.0 = themed.color(&theme);
+    }
+}
+
+// Ships and lasers already in flight take the new palette too
+fn recolor_sprites(
+    mut ships: Query<(&Player, &mut Sprite), Without<Laser>>,
+    mut lasers: Query<&mut Sprite, With<Laser>>,
+    theme: Res<Theme>,
+) {
+    for (player, mut sprite) in &mut ships {
+        sprite.color = theme.players[player.0];
+    }
+    for mut sprite in &mut lasers {
+        sprite.color = theme.laser;
+    }
+}
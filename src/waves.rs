@@ -0,0 +1,67 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{
+    GameAssets, GameMode, GameState,
+    game::{ASTEROID_SIZE, ASTEROID_SPEED, Asteroid, spawn_asteroid},
+};
+
+pub fn waves_plugin(app: &mut App) {
+    app.add_systems(OnEnter(GameState::Game), reset_waves)
+        .add_systems(
+            Update,
+            spawn_wave.run_if(in_state(GameState::Game).and(resource_equals(GameMode::Endless))),
+        );
+}
+
+#[derive(Resource)]
+pub struct Wave(pub u32);
+
+fn reset_waves(mut commands: Commands) {
+    commands.insert_resource(Wave(0));
+}
+
+fn spawn_wave(
+    mut commands: Commands,
+    mut wave: ResMut<Wave>,
+    asteroids: Query<(), With<Asteroid>>,
+    camera: Query<(&Transform, &Projection), With<Camera2d>>,
+    windows: Query<&Window>,
+    game_assets: Res<GameAssets>,
+) -> Result {
+    if asteroids.iter().len() > 2 {
+        return Ok(());
+    }
+    wave.0 += 1;
+
+    let (camera_transform, projection) = camera.single()?;
+    let scale = match projection {
+        Projection::Orthographic(orthographic) => orthographic.scale,
+        _ => 1.0,
+    };
+    let center = camera_transform.translation.xy();
+    let viewport = windows
+        .single()
+        .map(Window::size)
+        .unwrap_or(Vec2::new(1280.0, 720.0));
+    let distance = viewport.length() / 2.0 * scale + 100.0;
+
+    let difficulty = (wave.0 - 1) as f32;
+    let mut rng = rand::thread_rng();
+    for _ in 0..(4 + wave.0 * 2) {
+        let direction = Vec2::from_angle(rng.gen_range(0.0..TAU));
+        let heading = Vec2::from_angle(rng.gen_range(-0.5..0.5)).rotate(-direction);
+        spawn_asteroid(
+            &mut commands,
+            game_assets.as_ref(),
+            center + direction * distance,
+            heading * rng.gen_range(ASTEROID_SPEED) * (1.0 + difficulty * 0.15),
+            rng.gen_range(ASTEROID_SIZE),
+            1.0 + difficulty * 0.25,
+        );
+    }
+
+    Ok(())
+}
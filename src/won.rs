@@ -1,13 +1,13 @@
-use bevy::{color::palettes, prelude::*};
+use bevy::prelude::*;
 
-use crate::GameState;
+use crate::{GameState, game::Winner, localization::Localized, theme::Themed};
 
 pub fn won_plugin(app: &mut App) {
     app.add_systems(OnEnter(GameState::Won), display_text)
         .add_systems(Update, back_to_menu.run_if(in_state(GameState::Won)));
 }
 
-fn display_text(mut commands: Commands) {
+fn display_text(mut commands: Commands, winner: Option<Res<Winner>>) {
     commands.spawn((
         Node {
             width: Val::Percent(100.0),
@@ -19,20 +19,25 @@ fn display_text(mut commands: Commands) {
         },
         children![
             (
-                Text::new("You Won!"),
+                Text::default(),
+                match winner {
+                    Some(winner) => Localized::new("won-player").with_arg("player", winner.0 + 1),
+                    None => Localized::new("won-solo"),
+                },
                 TextFont {
                     font_size: 200.0,
                     ..default()
                 },
-                TextColor::from(palettes::tailwind::RED_600),
+                Themed::Title,
             ),
             (
-                Text::new("Press any key to go back to the menu"),
+                Text::default(),
+                Localized::new("won-back"),
                 TextFont {
                     font_size: 50.0,
                     ..default()
                 },
-                TextColor::from(palettes::tailwind::RED_800),
+                Themed::Text,
             )
         ],
         StateScoped(GameState::Won),